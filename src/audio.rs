@@ -1,13 +1,69 @@
+use std::io::{Read, Seek, SeekFrom};
 use std::path::Path;
 use std::process::Command as ProcessCommand;
+use std::time::Duration;
 use tempfile::NamedTempFile;
 
+/// Playback rates accepted by `--playback-rate` and the `playback_rate` preset
+/// field; outside this range mpv's time-stretching becomes unintelligible.
+pub const PLAYBACK_RATE_RANGE: std::ops::RangeInclusive<f64> = 0.25..=4.0;
+
+#[derive(Debug, Clone, Default)]
+pub struct PlaybackOptions {
+    pub device: Option<String>,
+    pub volume: Option<u32>,
+    pub rate: Option<f64>,
+}
+
+/// Parses a `--playback-rate` value, rejecting rates outside
+/// [`PLAYBACK_RATE_RANGE`].
+pub fn parse_playback_rate(value: &str) -> Result<f64, String> {
+    let rate: f64 = value
+        .parse()
+        .map_err(|_| format!("'{}' is not a number", value))?;
+    if !PLAYBACK_RATE_RANGE.contains(&rate) {
+        return Err(format!(
+            "{} is not between {:?} and {:?}",
+            value,
+            PLAYBACK_RATE_RANGE.start(),
+            PLAYBACK_RATE_RANGE.end()
+        ));
+    }
+    Ok(rate)
+}
+
 pub fn play_audio_and_cleanup(file_path: &Path) -> Result<(), Box<dyn std::error::Error>> {
-    let status = ProcessCommand::new("mpv")
-        .arg("--no-video")
-        .arg("--really-quiet")
-        .arg(file_path)
-        .status()?;
+    play_audio_and_cleanup_with_options(file_path, &PlaybackOptions::default())
+}
+
+pub fn play_audio_and_cleanup_with_options(
+    file_path: &Path,
+    options: &PlaybackOptions,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut command = ProcessCommand::new("mpv");
+    command.arg("--no-video").arg("--really-quiet");
+
+    if let Some(ref device) = options.device {
+        command.arg(format!("--audio-device={}", device));
+    }
+    if let Some(volume) = options.volume {
+        command.arg(format!("--volume={}", volume));
+    }
+    if let Some(rate) = options.rate {
+        if !PLAYBACK_RATE_RANGE.contains(&rate) {
+            return Err(format!(
+                "Playback rate {} is not between {:?} and {:?}",
+                rate,
+                PLAYBACK_RATE_RANGE.start(),
+                PLAYBACK_RATE_RANGE.end()
+            )
+            .into());
+        }
+        // mpv time-stretches with scaletempo by default, so pitch is preserved
+        command.arg(format!("--speed={}", rate));
+    }
+
+    let status = command.arg(file_path).status()?;
 
     if !status.success() {
         return Err("mpv failed to play audio".into());
@@ -23,3 +79,69 @@ pub fn create_temp_audio_file() -> Result<std::path::PathBuf, Box<dyn std::error
     temp_file.persist(&temp_path)?;
     Ok(temp_path)
 }
+
+pub fn wav_duration(file_path: &Path) -> Result<Duration, Box<dyn std::error::Error>> {
+    let mut file = std::fs::File::open(file_path)?;
+
+    let mut riff_header = [0u8; 12];
+    file.read_exact(&mut riff_header)?;
+    if &riff_header[0..4] != b"RIFF" || &riff_header[8..12] != b"WAVE" {
+        return Err(format!("Not a WAV file: {}", file_path.display()).into());
+    }
+
+    let mut byte_rate: Option<u32> = None;
+    let mut chunk_header = [0u8; 8];
+
+    // Walk RIFF chunks until both "fmt " and "data" have been seen
+    while file.read_exact(&mut chunk_header).is_ok() {
+        let chunk_size = u32::from_le_bytes(chunk_header[4..8].try_into()?);
+
+        match &chunk_header[0..4] {
+            b"fmt " => {
+                let mut fmt = vec![0u8; chunk_size as usize];
+                file.read_exact(&mut fmt)?;
+                if fmt.len() < 12 {
+                    return Err("Malformed WAV fmt chunk".into());
+                }
+                byte_rate = Some(u32::from_le_bytes(fmt[8..12].try_into()?));
+            }
+            b"data" => {
+                let byte_rate = byte_rate
+                    .filter(|rate| *rate > 0)
+                    .ok_or("WAV data chunk found before a valid fmt chunk")?;
+                return Ok(Duration::from_secs_f64(
+                    chunk_size as f64 / byte_rate as f64,
+                ));
+            }
+            _ => {
+                file.seek(SeekFrom::Current(chunk_size as i64))?;
+            }
+        }
+
+        // Chunks are padded to an even number of bytes
+        if chunk_size % 2 == 1 {
+            file.seek(SeekFrom::Current(1))?;
+        }
+    }
+
+    Err(format!("No audio data found in {}", file_path.display()).into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bounds_playback_rates() {
+        assert_eq!(parse_playback_rate("1.5"), Ok(1.5));
+        assert_eq!(parse_playback_rate("0.25"), Ok(0.25));
+        assert_eq!(parse_playback_rate("4"), Ok(4.0));
+        for value in ["0", "-1", "0.2", "4.5", "NaN", "inf", "fast"] {
+            assert!(
+                parse_playback_rate(value).is_err(),
+                "{} was accepted",
+                value
+            );
+        }
+    }
+}
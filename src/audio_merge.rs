@@ -1,5 +1,10 @@
-use std::path::Path;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use std::process::Command;
+use std::time::Duration;
+
+/// Silence inserted between merged chunks.
+pub const CHUNK_GAP: Duration = Duration::from_secs(1);
 
 pub fn check_ffmpeg_available() -> bool {
     Command::new("ffmpeg")
@@ -14,12 +19,28 @@ pub fn check_ffmpeg_available() -> bool {
 pub fn merge_audio_files(
     input_files: &[&Path],
     output_path: &Path,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let gaps: Vec<Duration> = (0..input_files.len())
+        .map(|i| if i == 0 { Duration::ZERO } else { CHUNK_GAP })
+        .collect();
+    merge_audio_files_with_gaps(input_files, &gaps, output_path)
+}
+
+/// Concatenates `input_files`, inserting `gaps[i]` of silence before
+/// `input_files[i]`.
+pub fn merge_audio_files_with_gaps(
+    input_files: &[&Path],
+    gaps: &[Duration],
+    output_path: &Path,
 ) -> Result<(), Box<dyn std::error::Error>> {
     if input_files.is_empty() {
         return Err("No input files provided".into());
     }
+    if gaps.len() != input_files.len() {
+        return Err("Number of gaps must match number of input files".into());
+    }
 
-    if input_files.len() == 1 {
+    if input_files.len() == 1 && gaps[0].is_zero() {
         // Single file, just copy
         std::fs::copy(input_files[0], output_path)?;
         return Ok(());
@@ -29,35 +50,27 @@ pub fn merge_audio_files(
     let temp_dir = tempfile::tempdir()?;
     let concat_file = temp_dir.path().join("concat_list.txt");
 
-    // Create 1-second silence audio file
-    let silence_path = temp_dir.path().join("silence.wav");
-    let silence_status = Command::new("ffmpeg")
-        .arg("-f")
-        .arg("lavfi")
-        .arg("-i")
-        .arg("anullsrc=channel_layout=mono:sample_rate=44100")
-        .arg("-t")
-        .arg("1") // 1 second
-        .arg("-y")
-        .arg("-loglevel")
-        .arg("error")
-        .arg(&silence_path)
-        .status()?;
-
-    if !silence_status.success() {
-        return Err("Failed to create silence audio".into());
+    // Create one silence file per distinct gap length
+    let mut silence_files: HashMap<u128, PathBuf> = HashMap::new();
+    for gap in gaps.iter().filter(|gap| !gap.is_zero()) {
+        let millis = silence_millis(*gap);
+        if silence_files.contains_key(&millis) {
+            continue;
+        }
+        let silence_path = temp_dir.path().join(format!("silence_{}.wav", millis));
+        create_silence(Duration::from_millis(millis as u64), &silence_path)?;
+        silence_files.insert(millis, silence_path);
     }
 
-    // Create new concat file with silence between audio files
-    let mut new_concat_content = String::new();
-    for (i, file) in input_files.iter().enumerate() {
-        new_concat_content.push_str(&format!("file '{}'\n", file.display()));
-        // Add silence between files (but not after the last file)
-        if i < input_files.len() - 1 {
-            new_concat_content.push_str(&format!("file '{}'\n", silence_path.display()));
+    // Create concat file with silence between audio files
+    let mut concat_content = String::new();
+    for (file, gap) in input_files.iter().zip(gaps) {
+        if let Some(silence_path) = silence_files.get(&silence_millis(*gap)) {
+            concat_content.push_str(&format!("file '{}'\n", silence_path.display()));
         }
+        concat_content.push_str(&format!("file '{}'\n", file.display()));
     }
-    std::fs::write(&concat_file, new_concat_content)?;
+    std::fs::write(&concat_file, concat_content)?;
 
     // Run ffmpeg to concatenate files (suppress output)
     let status = Command::new("ffmpeg")
@@ -81,3 +94,79 @@ pub fn merge_audio_files(
 
     Ok(())
 }
+
+// Silence files are whole milliseconds; shorter gaps are rounded up so they
+// are not mistaken for no gap
+fn silence_millis(gap: Duration) -> u128 {
+    gap.as_nanos().div_ceil(1_000_000)
+}
+
+/// Converts `input` to the format implied by `output_path`'s extension.
+pub fn convert_audio(input: &Path, output_path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let status = Command::new("ffmpeg")
+        .arg("-i")
+        .arg(input)
+        .arg("-y")
+        .arg("-loglevel")
+        .arg("error")
+        .arg(output_path)
+        .status()?;
+
+    if !status.success() {
+        return Err("ffmpeg failed to convert audio".into());
+    }
+
+    Ok(())
+}
+
+/// Converts `input` to raw 16-bit little-endian mono PCM at `sample_rate`.
+pub fn convert_to_pcm(
+    input: &Path,
+    output_path: &Path,
+    sample_rate: u32,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let status = Command::new("ffmpeg")
+        .arg("-i")
+        .arg(input)
+        .arg("-f")
+        .arg("s16le")
+        .arg("-ac")
+        .arg("1")
+        .arg("-ar")
+        .arg(sample_rate.to_string())
+        .arg("-y")
+        .arg("-loglevel")
+        .arg("error")
+        .arg(output_path)
+        .status()?;
+
+    if !status.success() {
+        return Err("ffmpeg failed to convert audio".into());
+    }
+
+    Ok(())
+}
+
+fn create_silence(
+    duration: Duration,
+    output_path: &Path,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let status = Command::new("ffmpeg")
+        .arg("-f")
+        .arg("lavfi")
+        .arg("-i")
+        .arg("anullsrc=channel_layout=mono:sample_rate=44100")
+        .arg("-t")
+        .arg(duration.as_secs_f64().to_string())
+        .arg("-y")
+        .arg("-loglevel")
+        .arg("error")
+        .arg(output_path)
+        .status()?;
+
+    if !status.success() {
+        return Err("Failed to create silence audio".into());
+    }
+
+    Ok(())
+}
@@ -0,0 +1,215 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+use crate::config::{Config, VoiceOptions};
+use crate::segment::Segment;
+use crate::voicepeak::{engine_version, VoiceSettings};
+
+pub const BATCH_REPORT_FILE_NAME: &str = "batch-report.json";
+
+/// One line to render, read from a CSV or JSONL batch file.
+#[derive(Debug, Clone, Deserialize)]
+pub struct BatchRow {
+    pub id: String,
+    pub text: String,
+    /// Preset name, or a speaker label mapped in `[speakers]`.
+    #[serde(default)]
+    pub preset: Option<String>,
+    #[serde(default)]
+    pub narrator: Option<String>,
+    #[serde(default)]
+    pub emotion: Option<String>,
+    #[serde(default)]
+    pub pitch: Option<i32>,
+    #[serde(default)]
+    pub speed: Option<i32>,
+    /// Output file name relative to the output directory (default: `<id>.wav`).
+    #[serde(default)]
+    pub output: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BatchStatus {
+    Rendered,
+    /// The output existed and was rendered from the same text and voice.
+    Skipped,
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchResult {
+    pub id: String,
+    pub output: String,
+    pub status: BatchStatus,
+    /// Hash of the chunks the output was rendered from, used to detect
+    /// changed rows on the next run.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub key: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct BatchReport {
+    pub rows: Vec<BatchResult>,
+}
+
+impl BatchRow {
+    pub fn output_file_name(&self) -> String {
+        self.output
+            .clone()
+            .unwrap_or_else(|| format!("{}.wav", self.id))
+    }
+
+    /// Resolves the row's voice from its preset, narrator, emotion, pitch and
+    /// speed columns.
+    pub fn voice(&self, config: &Config) -> Result<VoiceSettings, Box<dyn std::error::Error>> {
+        VoiceOptions {
+            preset: self.preset.clone(),
+            narrator: self.narrator.clone(),
+            emotion: self.emotion.clone(),
+            pitch: self.pitch,
+            speed: self.speed,
+        }
+        .resolve(config)
+    }
+}
+
+/// Reads batch rows from a JSONL file (`.jsonl`, `.ndjson`) or a CSV file
+/// with a header row. Columns other than `id` and `text` are optional.
+pub fn read_batch_file(path: &Path) -> Result<Vec<BatchRow>, Box<dyn std::error::Error>> {
+    let is_jsonl = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.to_ascii_lowercase())
+        .is_some_and(|ext| matches!(ext.as_str(), "jsonl" | "ndjson"));
+
+    let rows = if is_jsonl {
+        let content = fs::read_to_string(path)?;
+        let mut rows = Vec::new();
+        for (line_number, line) in content.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let row: BatchRow = serde_json::from_str(line)
+                .map_err(|e| format!("{} line {}: {}", path.display(), line_number + 1, e))?;
+            rows.push(row);
+        }
+        rows
+    } else {
+        let mut reader = csv::ReaderBuilder::new()
+            .trim(csv::Trim::All)
+            .from_path(path)?;
+        let mut rows = Vec::new();
+        for record in reader.deserialize() {
+            let row: BatchRow = record.map_err(|e| format!("{}: {}", path.display(), e))?;
+            rows.push(row);
+        }
+        rows
+    };
+
+    let mut ids = HashSet::new();
+    let mut outputs = HashSet::new();
+    for row in &rows {
+        if row.id.is_empty() {
+            return Err(format!("{}: row with empty id", path.display()).into());
+        }
+        if !ids.insert(row.id.as_str()) {
+            return Err(format!("{}: duplicate id '{}'", path.display(), row.id).into());
+        }
+        let output = row.output_file_name();
+        let escapes = Path::new(&output)
+            .components()
+            .any(|component| !matches!(component, Component::Normal(_) | Component::CurDir));
+        if escapes {
+            return Err(format!(
+                "{}: output '{}' of row '{}' must be a relative path inside the output directory",
+                path.display(),
+                output,
+                row.id
+            )
+            .into());
+        }
+        if !outputs.insert(row.output_file_name()) {
+            return Err(format!(
+                "{}: output '{}' is used by more than one row",
+                path.display(),
+                row.output_file_name()
+            )
+            .into());
+        }
+    }
+
+    Ok(rows)
+}
+
+/// Identifies what an output was rendered from: the final chunk texts and
+/// voices, after dictionaries and normalization, and the VOICEPEAK version.
+pub fn render_key(chunks: &[Segment]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(engine_version().as_bytes());
+    hasher.update([0]);
+    for chunk in chunks {
+        for field in [
+            chunk.text.as_str(),
+            &chunk.voice.narrator,
+            &chunk.voice.emotion,
+            chunk.voice.pitch.as_deref().unwrap_or_default(),
+            chunk.voice.speed.as_deref().unwrap_or_default(),
+        ] {
+            hasher.update(field.as_bytes());
+            hasher.update([0]);
+        }
+    }
+
+    hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+impl BatchReport {
+    pub fn path(out_dir: &Path) -> PathBuf {
+        out_dir.join(BATCH_REPORT_FILE_NAME)
+    }
+
+    /// Loads the report of a previous run, or an empty report if there is none.
+    pub fn load(out_dir: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        let path = Self::path(out_dir);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = fs::read_to_string(&path)?;
+        Ok(serde_json::from_str(&content).map_err(|e| format!("{}: {}", path.display(), e))?)
+    }
+
+    pub fn save(&self, out_dir: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        fs::write(Self::path(out_dir), serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Whether `output` in `out_dir` exists and was last rendered from `key`.
+    pub fn is_up_to_date(&self, out_dir: &Path, output: &str, key: &str) -> bool {
+        out_dir.join(output).is_file()
+            && self.rows.iter().any(|row| {
+                row.output == output && row.key == key && row.status != BatchStatus::Failed
+            })
+    }
+
+    /// Replaces the result recorded for the same row, or appends it.
+    pub fn record(&mut self, result: BatchResult) {
+        match self.rows.iter_mut().find(|row| row.id == result.id) {
+            Some(row) => *row = result,
+            None => self.rows.push(result),
+        }
+    }
+
+    pub fn count(&self, status: BatchStatus) -> usize {
+        self.rows.iter().filter(|row| row.status == status).count()
+    }
+}
@@ -0,0 +1,282 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::voicepeak::{engine_version, VoiceSettings};
+
+const CACHE_FILE_EXTENSION: &str = "wav";
+
+const PARTIAL_FILE_EXTENSION: &str = "partial";
+
+/// Partial files older than this were left by a run that was killed while
+/// writing an entry.
+const STALE_PARTIAL_AGE: Duration = Duration::from_secs(60 * 60);
+
+/// Minimum time between automatic prunes in one process, so follow, watch
+/// and serve do not scan the cache for every line or request.
+const PRUNE_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CacheConfig {
+    pub enabled: bool,
+    /// Least recently used entries are removed beyond this size.
+    pub max_size_mb: u64,
+    /// Entries not used for this many days are removed.
+    pub max_age_days: u64,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_size_mb: 500,
+            max_age_days: 30,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct CacheStats {
+    pub entries: usize,
+    pub bytes: u64,
+    pub oldest: Option<SystemTime>,
+}
+
+struct CacheEntry {
+    path: PathBuf,
+    size: u64,
+    last_used: SystemTime,
+}
+
+/// Synthesized chunks stored under the user cache directory, keyed by a hash
+/// of the text, voice parameters and engine version.
+#[derive(Debug, Clone)]
+pub struct SynthesisCache {
+    dir: PathBuf,
+    max_bytes: u64,
+    max_age: Duration,
+    engine_version: String,
+}
+
+pub fn get_cache_dir() -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let cache_dir = dirs::cache_dir().ok_or("Could not determine cache directory")?;
+    Ok(cache_dir.join("vp").join("synthesis"))
+}
+
+impl SynthesisCache {
+    pub fn open(config: &CacheConfig) -> Result<Self, Box<dyn std::error::Error>> {
+        let dir = get_cache_dir()?;
+        fs::create_dir_all(&dir)?;
+
+        Ok(Self {
+            dir,
+            max_bytes: config.max_size_mb.saturating_mul(1024 * 1024),
+            max_age: Duration::from_secs(config.max_age_days.saturating_mul(24 * 60 * 60)),
+            engine_version: engine_version(),
+        })
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn key(&self, text: &str, voice: &VoiceSettings) -> String {
+        let mut hasher = Sha256::new();
+        for field in [
+            text,
+            &voice.narrator,
+            &voice.emotion,
+            voice.pitch.as_deref().unwrap_or_default(),
+            voice.speed.as_deref().unwrap_or_default(),
+            &self.engine_version,
+        ] {
+            hasher.update(field.as_bytes());
+            // Separator so ("ab", "c") and ("a", "bc") hash differently
+            hasher.update([0]);
+        }
+
+        hasher
+            .finalize()
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect()
+    }
+
+    fn entry_path(&self, key: &str) -> PathBuf {
+        self.dir.join(key).with_extension(CACHE_FILE_EXTENSION)
+    }
+
+    /// Copies the cached audio for `key` to `output`. Returns `false` on a miss.
+    pub fn get(&self, key: &str, output: &Path) -> Result<bool, Box<dyn std::error::Error>> {
+        let entry = self.entry_path(key);
+        if !entry.is_file() {
+            return Ok(false);
+        }
+
+        fs::copy(&entry, output)?;
+        // Entries are evicted least recently used first
+        fs::File::options()
+            .append(true)
+            .open(&entry)?
+            .set_modified(SystemTime::now())?;
+        Ok(true)
+    }
+
+    pub fn put(&self, key: &str, audio: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        let entry = self.entry_path(key);
+        // Write under a temporary name so concurrent runs never read a partial
+        // file. The counter keeps threads of one process (`vp serve`) apart.
+        static NEXT_PARTIAL: AtomicU64 = AtomicU64::new(0);
+        let partial = self.dir.join(format!(
+            ".{}.{}.{}.{}",
+            key,
+            std::process::id(),
+            NEXT_PARTIAL.fetch_add(1, Ordering::Relaxed),
+            PARTIAL_FILE_EXTENSION
+        ));
+        let result = fs::copy(audio, &partial).and_then(|_| fs::rename(&partial, &entry));
+        if result.is_err() {
+            let _ = fs::remove_file(&partial);
+        }
+        Ok(result?)
+    }
+
+    /// Files in the cache directory with the given extension. Files removed
+    /// by a concurrent run while listing are skipped.
+    fn files(&self, extension: &str) -> Result<Vec<CacheEntry>, Box<dyn std::error::Error>> {
+        let mut entries = Vec::new();
+
+        for dir_entry in fs::read_dir(&self.dir)? {
+            let Ok(dir_entry) = dir_entry else { continue };
+            let path = dir_entry.path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some(extension) {
+                continue;
+            }
+            let Ok((size, last_used)) =
+                fs::metadata(&path).and_then(|m| Ok((m.len(), m.modified()?)))
+            else {
+                continue;
+            };
+            entries.push(CacheEntry {
+                size,
+                last_used,
+                path,
+            });
+        }
+
+        Ok(entries)
+    }
+
+    fn entries(&self) -> Result<Vec<CacheEntry>, Box<dyn std::error::Error>> {
+        self.files(CACHE_FILE_EXTENSION)
+    }
+
+    pub fn stats(&self) -> Result<CacheStats, Box<dyn std::error::Error>> {
+        let entries = self.entries()?;
+
+        Ok(CacheStats {
+            entries: entries.len(),
+            bytes: entries.iter().map(|entry| entry.size).sum(),
+            oldest: entries.iter().map(|entry| entry.last_used).min(),
+        })
+    }
+
+    /// Removes every entry. Returns the number of removed entries.
+    pub fn clear(&self) -> Result<usize, Box<dyn std::error::Error>> {
+        let entries = self.entries()?;
+        for entry in &entries {
+            fs::remove_file(&entry.path)?;
+        }
+        Ok(entries.len())
+    }
+
+    /// Removes entries older than the age limit, then the least recently used
+    /// ones until the cache fits the size limit. Stale partial files are
+    /// removed too. Returns the number of removed entries.
+    pub fn prune(&self) -> Result<usize, Box<dyn std::error::Error>> {
+        let now = SystemTime::now();
+        for partial in self.files(PARTIAL_FILE_EXTENSION)? {
+            let stale = now
+                .duration_since(partial.last_used)
+                .is_ok_and(|age| age > STALE_PARTIAL_AGE);
+            if stale {
+                let _ = fs::remove_file(&partial.path);
+            }
+        }
+
+        let mut entries = self.entries()?;
+        // Newest first, so the oldest entries are popped from the end
+        entries.sort_by_key(|entry| std::cmp::Reverse(entry.last_used));
+
+        let mut total: u64 = entries.iter().map(|entry| entry.size).sum();
+        let mut removed = 0;
+
+        while let Some(entry) = entries.last() {
+            let expired = now
+                .duration_since(entry.last_used)
+                .is_ok_and(|age| age > self.max_age);
+            if !expired && total <= self.max_bytes {
+                break;
+            }
+            // Another run may have removed it already
+            match fs::remove_file(&entry.path) {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+                _ => {}
+            }
+            total -= entry.size;
+            removed += 1;
+            entries.pop();
+        }
+
+        Ok(removed)
+    }
+
+    /// Prunes like [`prune`](Self::prune), unless this process already did
+    /// within [`PRUNE_INTERVAL`]. Returns `None` when skipped.
+    pub fn prune_if_due(&self) -> Result<Option<usize>, Box<dyn std::error::Error>> {
+        static LAST_PRUNE: Mutex<Option<Instant>> = Mutex::new(None);
+        {
+            let mut last = LAST_PRUNE.lock().unwrap_or_else(|e| e.into_inner());
+            if last.is_some_and(|at| at.elapsed() < PRUNE_INTERVAL) {
+                return Ok(None);
+            }
+            *last = Some(Instant::now());
+        }
+        self.prune().map(Some)
+    }
+}
+
+pub fn print_cache_stats(
+    cache: &SynthesisCache,
+    config: &CacheConfig,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let stats = cache.stats()?;
+
+    println!("Cache: {}", cache.dir().display());
+    println!("  Entries: {}", stats.entries);
+    println!(
+        "  Size: {:.1} MB (limit: {} MB)",
+        stats.bytes as f64 / (1024.0 * 1024.0),
+        config.max_size_mb
+    );
+    if let Some(age) = stats
+        .oldest
+        .and_then(|oldest| SystemTime::now().duration_since(oldest).ok())
+    {
+        println!(
+            "  Least recently used: {} day(s) ago (limit: {} days)",
+            age.as_secs() / (24 * 60 * 60),
+            config.max_age_days
+        );
+    }
+    if !config.enabled {
+        println!("  Caching is disabled in config");
+    }
+
+    Ok(())
+}
@@ -1,12 +1,48 @@
 use clap::{Arg, Command};
+use serde::Serialize;
 use std::io::{self, IsTerminal, Read};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::thread;
 
-use crate::audio::{create_temp_audio_file, play_audio_and_cleanup};
-use crate::audio_merge::{check_ffmpeg_available, merge_audio_files};
-use crate::config::{get_presets_map, list_presets, load_config, Config};
-use crate::text_splitter::{check_text_length, split_text, MAX_CHARS};
-use crate::voicepeak::{list_emotion, list_narrator, VoicepeakCommand};
+use crate::audio::{
+    create_temp_audio_file, parse_playback_rate, play_audio_and_cleanup_with_options, wav_duration,
+    PlaybackOptions,
+};
+use crate::audio_merge::{check_ffmpeg_available, merge_audio_files_with_gaps};
+use crate::batch::{read_batch_file, render_key, BatchReport, BatchResult, BatchStatus};
+use crate::cache::{print_cache_stats, SynthesisCache};
+use crate::config::{
+    get_presets_map, list_presets, load_config, save_preset, Config, VoiceOptions, VoicePreset,
+};
+#[cfg(unix)]
+use crate::daemon::{
+    get_socket_path, run_daemon, DaemonClient, DaemonJob, DaemonJobState, DaemonRequest,
+    DaemonResponse, JobRequest,
+};
+use crate::dialogue::parse_dialogue;
+use crate::dictionary::{
+    find_project_dictionary, get_dictionary_path, list_dictionary, load_dictionaries,
+    load_dictionary, save_dictionary, DictionaryEntry, PROJECT_DICTIONARY_FILE_NAME,
+};
+use crate::follow::{LineFilter, LineQueue};
+use crate::html::{is_html_path, parse_html};
+use crate::job::{list_jobs, Job};
+use crate::manifest::{write_split_output, MANIFEST_FILE_NAME};
+use crate::markdown::{is_markdown_path, parse_markdown};
+use crate::markup::{parse_duration, parse_markup};
+use crate::metadata::{write_metadata, AudioMetadata};
+use crate::normalize::normalize_text;
+use crate::render::{generate_chunks, render_to_file, synthesize_chunk, TextPipeline};
+use crate::repl::{get_history_path, parse_repl_line, ReplCommand, ReplState, REPL_HELP};
+use crate::segment::{chunk_segments_with_boundaries, segment_gaps, Segment};
+use crate::server::{serve, CompatApi, ServerState, DEFAULT_LISTEN_ADDRESS};
+use crate::ssml::{looks_like_ssml, parse_ssml};
+use crate::subtitles::{build_cues, write_subtitles, SubtitleFormat, SubtitleUnit};
+use crate::text_splitter::{Boundary, TextSplitter};
+use crate::voicepeak::{list_emotion, list_narrator, VoiceSettings};
+use crate::voicevox::VOICEVOX_LISTEN_ADDRESS;
+use crate::watch::{FileTail, FileWatcher, WATCH_INTERVAL};
 
 pub fn build_cli() -> Command {
     Command::new("voicepeak-cli")
@@ -33,6 +69,45 @@ pub fn build_cli() -> Command {
                 .value_name("FILE")
                 .help("Path of output file (optional - will play with mpv if not specified)"),
         )
+        .arg(
+            Arg::new("split-output")
+                .long("split-output")
+                .value_name("DIR")
+                .help("Keep each chunk as a numbered WAV in DIR with a manifest.json"),
+        )
+        .arg(
+            Arg::new("subtitles")
+                .long("subtitles")
+                .value_name("FILE")
+                .help("Write subtitles for the output file (.srt or .vtt)")
+                .requires("out"),
+        )
+        .arg(
+            Arg::new("subtitle-unit")
+                .long("subtitle-unit")
+                .value_name("UNIT")
+                .help("Subtitle cue unit: chunk or sentence (default: chunk)")
+                .value_parser(["chunk", "sentence"])
+                .default_value("chunk"),
+        )
+        .arg(
+            Arg::new("tag")
+                .long("tag")
+                .help("Write title, artist, comment and source text tags into output files")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("title")
+                .long("title")
+                .value_name("TEXT")
+                .help("Title tag for output files (implies --tag)"),
+        )
+        .arg(
+            Arg::new("artist")
+                .long("artist")
+                .value_name("NAME")
+                .help("Artist tag for output files (implies --tag, default: narrator)"),
+        )
         .arg(
             Arg::new("narrator")
                 .short('n')
@@ -52,8 +127,42 @@ pub fn build_cli() -> Command {
                 .short('p')
                 .long("preset")
                 .value_name("NAME")
-                .help("Use voice preset (use --list-presets to see available presets)")
-                .conflicts_with_all(["narrator", "emotion"]),
+                .help("Use voice preset (use --list-presets to see available presets)"),
+        )
+        .arg(
+            Arg::new("markup")
+                .long("markup")
+                .help("Parse inline tags like [narrator=NAME], [preset NAME] and [pause 800ms]")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("ssml")
+                .long("ssml")
+                .help("Parse input as SSML (auto-detected when input starts with <speak>)")
+                .action(clap::ArgAction::SetTrue)
+                .conflicts_with("markup"),
+        )
+        .arg(
+            Arg::new("script")
+                .long("script")
+                .help("Parse input as a dialogue script of `SPEAKER: text` lines")
+                .action(clap::ArgAction::SetTrue)
+                .conflicts_with_all(["markup", "ssml"]),
+        )
+        .arg(
+            Arg::new("input-format")
+                .long("input-format")
+                .value_name("FORMAT")
+                .help("Input format: text, markdown or html (default: detected from the file extension)")
+                .value_parser(["text", "markdown", "html"])
+                .conflicts_with_all(["markup", "ssml", "script"]),
+        )
+        .arg(
+            Arg::new("turn-gap")
+                .long("turn-gap")
+                .value_name("DURATION")
+                .help("Silence between speaker turns in --script mode (e.g., 500ms, 1.5s)")
+                .default_value("1s"),
         )
         .arg(
             Arg::new("list-narrator")
@@ -85,18 +194,57 @@ pub fn build_cli() -> Command {
                 .value_name("VALUE")
                 .help("Pitch (-300 - 300)"),
         )
+        .arg(
+            Arg::new("device")
+                .long("device")
+                .value_name("NAME")
+                .help("Audio output device for playback (see `mpv --audio-device=help`)"),
+        )
+        .arg(
+            Arg::new("volume")
+                .long("volume")
+                .value_name("VALUE")
+                .help("Playback volume (0 - 100)")
+                .value_parser(clap::value_parser!(u32).range(0..=100)),
+        )
+        .arg(
+            Arg::new("playback-rate")
+                .long("playback-rate")
+                .value_name("RATE")
+                .help("Playback rate without re-synthesis, 0.25 - 4.0 (e.g., 1.5)")
+                .value_parser(parse_playback_rate),
+        )
+        .arg(
+            Arg::new("max-chars")
+                .long("max-chars")
+                .value_name("N")
+                .help("Maximum characters per chunk (default: 140)")
+                .value_parser(clap::value_parser!(u32).range(1..)),
+        )
+        .arg(
+            Arg::new("sentence-terminators")
+                .long("sentence-terminators")
+                .value_name("CHARS")
+                .help("Characters that end a sentence (default: 。！？.!?)"),
+        )
+        .arg(
+            Arg::new("soft-breaks")
+                .long("soft-breaks")
+                .value_name("CHARS")
+                .help("Characters to break long sentences at (default: 、，, and spaces)"),
+        )
         .arg(
             Arg::new("strict-length")
                 .long("strict-length")
-                .help("Reject input longer than 140 characters (default: false, allows splitting)")
+                .help("Reject input longer than the maximum chunk length (default: false, allows splitting)")
                 .action(clap::ArgAction::SetTrue),
         )
         .arg(
             Arg::new("playback-mode")
                 .long("playback-mode")
                 .value_name("MODE")
-                .help("Playback mode: sequential or batch (default: batch)")
-                .value_parser(["sequential", "batch"])
+                .help("Playback mode: sequential, batch or stream (default: batch)")
+                .value_parser(["sequential", "batch", "stream"])
                 .default_value("batch"),
         )
         .arg(
@@ -112,33 +260,1079 @@ pub fn build_cli() -> Command {
                 .help("Run in background (return immediately)")
                 .action(clap::ArgAction::SetTrue),
         )
+        .arg(
+            Arg::new("follow")
+                .long("follow")
+                .visible_alias("lines")
+                .help("Speak each line from stdin as it arrives instead of waiting for the end of input")
+                .conflicts_with_all(["text", "file", "out", "split-output", "subtitles", "bg"])
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("include")
+                .long("include")
+                .value_name("REGEX")
+                .help("With --follow, only speak lines matching REGEX (repeatable)")
+                .requires("follow")
+                .action(clap::ArgAction::Append),
+        )
+        .arg(
+            Arg::new("exclude")
+                .long("exclude")
+                .value_name("REGEX")
+                .help("With --follow, skip lines matching REGEX (repeatable)")
+                .requires("follow")
+                .action(clap::ArgAction::Append),
+        )
+        .arg(
+            Arg::new("max-backlog")
+                .long("max-backlog")
+                .value_name("N")
+                .help("With --follow, drop the oldest waiting lines beyond N (default: 10)")
+                .requires("follow")
+                .value_parser(clap::value_parser!(u32).range(1..)),
+        )
+        .arg(
+            Arg::new("min-interval")
+                .long("min-interval")
+                .value_name("DURATION")
+                .help("With --follow, start speaking lines at most once per DURATION (e.g., 5s)")
+                .requires("follow"),
+        )
+        .arg(
+            Arg::new("dry-run")
+                .long("dry-run")
+                .help("Print how the text will be split into chunks without running VOICEPEAK")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("json")
+                .long("json")
+                .help("Print --dry-run output as JSON")
+                .action(clap::ArgAction::SetTrue)
+                .requires("dry-run"),
+        )
+        .arg(
+            Arg::new("resume")
+                .long("resume")
+                .help("Keep parts already rendered by an interrupted run of the same command")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("no-cache")
+                .long("no-cache")
+                .help("Do not read or write the synthesis cache")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("no-dict")
+                .long("no-dict")
+                .help("Do not apply pronunciation dictionaries")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("no-normalize")
+                .long("no-normalize")
+                .help("Do not normalize dates, currency, units, versions, URLs and emoji")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("no-daemon")
+                .long("no-daemon")
+                .help("Run directly even when a vp daemon is running")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .subcommand(build_dict_command())
+        .subcommand(
+            Command::new("resume")
+                .about("Finish an interrupted file render (lists unfinished jobs without JOB)")
+                .arg(Arg::new("job").value_name("JOB"))
+                .arg(
+                    Arg::new("discard")
+                        .long("discard")
+                        .help("Delete the job and its rendered parts instead of finishing it")
+                        .requires("job")
+                        .action(clap::ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new("verbose")
+                        .long("verbose")
+                        .short('v')
+                        .help("Enable verbose output (show VOICEPEAK debug messages)")
+                        .action(clap::ArgAction::SetTrue),
+                ),
+        )
+        .subcommand(build_batch_command())
+        .subcommand(
+            Command::new("repl")
+                .about("Speak lines as they are typed, changing voice settings with :commands")
+                .arg(
+                    Arg::new("preset")
+                        .short('p')
+                        .long("preset")
+                        .value_name("NAME")
+                        .help("Preset to start with"),
+                )
+                .arg(
+                    Arg::new("verbose")
+                        .long("verbose")
+                        .short('v')
+                        .help("Enable verbose output (show VOICEPEAK debug messages)")
+                        .action(clap::ArgAction::SetTrue),
+                ),
+        )
+        .subcommand(build_watch_command())
+        .subcommand(build_serve_command())
+        .subcommand(build_daemon_command())
+        .subcommand(
+            Command::new("cache")
+                .about("Manage the synthesis cache")
+                .subcommand_required(true)
+                .subcommand(Command::new("stats").about("Show cache location, size and age"))
+                .subcommand(Command::new("clear").about("Remove all cached audio"))
+                .subcommand(
+                    Command::new("prune").about("Remove entries beyond the configured size and age limits"),
+                ),
+        )
+}
+
+fn build_batch_command() -> Command {
+    Command::new("batch")
+        .about("Render each row of a CSV or JSONL file to its own audio file")
+        .arg(
+            Arg::new("file")
+                .value_name("FILE")
+                .required(true)
+                .help("CSV or JSONL rows with id, text and optional preset, narrator, emotion, pitch, speed and output"),
+        )
+        .arg(
+            Arg::new("out-dir")
+                .long("out-dir")
+                .value_name("DIR")
+                .required(true)
+                .help("Directory for the rendered files and batch-report.json"),
+        )
+        .arg(
+            Arg::new("force")
+                .long("force")
+                .help("Render every row, even if its output is up to date")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("no-cache")
+                .long("no-cache")
+                .help("Do not read or write the synthesis cache")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("no-dict")
+                .long("no-dict")
+                .help("Do not apply pronunciation dictionaries")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("no-normalize")
+                .long("no-normalize")
+                .help("Do not normalize dates, currency, units, versions, URLs and emoji")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("verbose")
+                .long("verbose")
+                .short('v')
+                .help("Enable verbose output (show VOICEPEAK debug messages)")
+                .action(clap::ArgAction::SetTrue),
+        )
+}
+
+fn build_watch_command() -> Command {
+    Command::new("watch")
+        .about("Speak text appended to a file, or re-render files in a directory when they change")
+        .arg(
+            Arg::new("path")
+                .value_name("PATH")
+                .required(true)
+                .help("File to follow, or file or directory to render with --out-dir"),
+        )
+        .arg(
+            Arg::new("out-dir")
+                .long("out-dir")
+                .value_name("DIR")
+                .help("Render each changed .txt/.md file to DIR instead of speaking appended text"),
+        )
+        .arg(
+            Arg::new("preset")
+                .short('p')
+                .long("preset")
+                .value_name("NAME")
+                .help("Use voice preset"),
+        )
+        .arg(
+            Arg::new("narrator")
+                .short('n')
+                .long("narrator")
+                .value_name("NAME")
+                .help("Name of voice"),
+        )
+        .arg(
+            Arg::new("emotion")
+                .short('e')
+                .long("emotion")
+                .value_name("EXPR")
+                .help("Emotion expression (e.g., happy=50,sad=50)"),
+        )
+        .arg(
+            Arg::new("speed")
+                .long("speed")
+                .value_name("VALUE")
+                .help("Speed (50 - 200)"),
+        )
+        .arg(
+            Arg::new("pitch")
+                .long("pitch")
+                .value_name("VALUE")
+                .help("Pitch (-300 - 300)"),
+        )
+        .arg(
+            Arg::new("verbose")
+                .long("verbose")
+                .short('v')
+                .help("Enable verbose output (show VOICEPEAK debug messages)")
+                .action(clap::ArgAction::SetTrue),
+        )
+}
+
+fn build_serve_command() -> Command {
+    Command::new("serve")
+        .about("Serve a local HTTP API for synthesis, voice listing and playback")
+        .arg(
+            Arg::new("listen")
+                .long("listen")
+                .value_name("ADDR")
+                .help(format!(
+                    "Address to listen on (default: {}, or {} with --compat voicevox)",
+                    DEFAULT_LISTEN_ADDRESS, VOICEVOX_LISTEN_ADDRESS
+                )),
+        )
+        .arg(
+            Arg::new("compat")
+                .long("compat")
+                .value_name("API")
+                .value_parser(["voicevox"])
+                .help("Also serve a compatible API for existing clients")
+                .action(clap::ArgAction::Append),
+        )
+        .arg(
+            Arg::new("no-cache")
+                .long("no-cache")
+                .help("Do not read or write the synthesis cache")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("no-dict")
+                .long("no-dict")
+                .help("Do not apply pronunciation dictionaries")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("no-normalize")
+                .long("no-normalize")
+                .help("Do not normalize dates, currency, units, versions, URLs and emoji")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("verbose")
+                .long("verbose")
+                .short('v')
+                .help("Log every request and show VOICEPEAK debug messages")
+                .action(clap::ArgAction::SetTrue),
+        )
+}
+
+fn build_daemon_command() -> Command {
+    let id_arg = Arg::new("id")
+        .value_name("ID")
+        .required(true)
+        .value_parser(clap::value_parser!(u64));
+
+    Command::new("daemon")
+        .about("Run jobs sent by other vp commands over a Unix socket, or manage its queue")
+        .subcommand(Command::new("list").about("List queued, running and recent jobs"))
+        .subcommand(
+            Command::new("status")
+                .about("Show the state of a job")
+                .arg(id_arg.clone()),
+        )
+        .subcommand(
+            Command::new("cancel")
+                .about("Cancel a queued job")
+                .arg(id_arg),
+        )
+}
+
+fn build_dict_command() -> Command {
+    let narrator_arg = Arg::new("narrator")
+        .short('n')
+        .long("narrator")
+        .value_name("NAME")
+        .help("Only apply to this narrator");
+    let project_arg = Arg::new("project")
+        .long("project")
+        .help("Use the project dictionary (.vp-dict.toml) instead of the user dictionary")
+        .action(clap::ArgAction::SetTrue);
+
+    Command::new("dict")
+        .about("Manage pronunciation dictionaries")
+        .subcommand_required(true)
+        .subcommand(
+            Command::new("add")
+                .about("Add or update a replacement rule")
+                .arg(Arg::new("pattern").value_name("PATTERN").required(true))
+                .arg(
+                    Arg::new("replacement")
+                        .value_name("REPLACEMENT")
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("regex")
+                        .long("regex")
+                        .help("Treat PATTERN as a regular expression ($1 etc. in REPLACEMENT)")
+                        .action(clap::ArgAction::SetTrue),
+                )
+                .arg(narrator_arg.clone())
+                .arg(project_arg.clone()),
+        )
+        .subcommand(
+            Command::new("remove")
+                .about("Remove replacement rules by pattern")
+                .arg(Arg::new("pattern").value_name("PATTERN").required(true))
+                .arg(narrator_arg.clone())
+                .arg(project_arg.clone()),
+        )
+        .subcommand(
+            Command::new("list")
+                .about("List replacement rules")
+                .arg(project_arg),
+        )
+        .subcommand(
+            Command::new("test")
+                .about("Show how text is rewritten by the dictionaries")
+                .arg(Arg::new("text").value_name("TEXT").required(true))
+                .arg(narrator_arg.help("Narrator to apply scoped rules for")),
+        )
+}
+
+/// Whether the parsed command needs VOICEPEAK and mpv to be installed.
+pub fn requires_environment(matches: &clap::ArgMatches) -> bool {
+    match matches.subcommand() {
+        Some(("dict" | "cache", _)) => false,
+        Some(("daemon", sub)) => sub.subcommand().is_none(),
+        Some(("resume", sub)) => sub.get_one::<String>("job").is_some() && !sub.get_flag("discard"),
+        _ => !matches.get_flag("dry-run"),
+    }
+}
+
+pub fn handle_matches(matches: clap::ArgMatches) -> Result<(), Box<dyn std::error::Error>> {
+    if let Some(("dict", dict_matches)) = matches.subcommand() {
+        return handle_dict_command(dict_matches);
+    }
+
+    let config = load_config()?;
+
+    if let Some(("cache", cache_matches)) = matches.subcommand() {
+        return handle_cache_command(cache_matches, &config);
+    }
+
+    if let Some(("resume", resume_matches)) = matches.subcommand() {
+        return handle_resume_command(resume_matches, &config);
+    }
+
+    if let Some(("batch", batch_matches)) = matches.subcommand() {
+        return handle_batch_command(batch_matches, &config);
+    }
+
+    if let Some(("watch", watch_matches)) = matches.subcommand() {
+        return handle_watch_command(watch_matches, &config);
+    }
+
+    if let Some(("serve", serve_matches)) = matches.subcommand() {
+        return handle_serve_command(serve_matches, config);
+    }
+
+    if let Some(("repl", repl_matches)) = matches.subcommand() {
+        return handle_repl_command(repl_matches, config);
+    }
+
+    if let Some(("daemon", daemon_matches)) = matches.subcommand() {
+        return handle_daemon_command(daemon_matches, config);
+    }
+
+    if matches.get_flag("list-narrator") {
+        list_narrator();
+        return Ok(());
+    }
+
+    if let Some(narrator) = matches.get_one::<String>("list-emotion") {
+        list_emotion(narrator);
+        return Ok(());
+    }
+
+    if matches.get_flag("list-presets") {
+        list_presets(&config);
+        return Ok(());
+    }
+
+    run_voicepeak(&matches, &config)
+}
+
+fn handle_resume_command(
+    matches: &clap::ArgMatches,
+    config: &Config,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let Some(id) = matches.get_one::<String>("job") else {
+        let jobs = list_jobs()?;
+        if jobs.is_empty() {
+            println!("No unfinished jobs");
+        }
+        for job in jobs {
+            let target = job
+                .state
+                .output
+                .as_ref()
+                .or(job.state.split_output.as_ref())
+                .map(|p| p.display().to_string())
+                .unwrap_or_default();
+            println!(
+                "{}  {}/{} parts  {}{}",
+                job.id(),
+                job.rendered_count(),
+                job.state.chunks.len(),
+                target,
+                if job.is_running() { "  (running)" } else { "" }
+            );
+        }
+        return Ok(());
+    };
+
+    if matches.get_flag("discard") {
+        Job::discard(id)?;
+        println!("Discarded job {}", id);
+        return Ok(());
+    }
+
+    let job = Job::open(id)?;
+    if job.state.output.is_some() && job.state.chunks.len() > 1 && !check_ffmpeg_available() {
+        return Err("ffmpeg is required to merge the rendered parts.\n\
+            Install ffmpeg: https://ffmpeg.org/download.html"
+            .into());
+    }
+
+    let cache = open_cache(config, false);
+    let splitter = TextSplitter::from_config(&config.splitter);
+    render_job(job, matches.get_flag("verbose"), cache.as_ref(), &splitter)
+}
+
+fn handle_batch_command(
+    matches: &clap::ArgMatches,
+    config: &Config,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let rows = read_batch_file(Path::new(matches.get_one::<String>("file").unwrap()))?;
+    let out_dir = PathBuf::from(matches.get_one::<String>("out-dir").unwrap());
+    std::fs::create_dir_all(&out_dir)?;
+
+    let previous = if matches.get_flag("force") {
+        BatchReport::default()
+    } else {
+        BatchReport::load(&out_dir)?
+    };
+    let pipeline = TextPipeline::new(
+        config,
+        !matches.get_flag("no-dict"),
+        !matches.get_flag("no-normalize"),
+    )?;
+    let cache = open_cache(config, matches.get_flag("no-cache"));
+    let verbose = matches.get_flag("verbose");
+
+    // Results of the previous run are kept until each row is processed again,
+    // so an interrupted batch still skips the rows it had finished before
+    let mut report = BatchReport {
+        rows: previous
+            .rows
+            .iter()
+            .filter(|result| rows.iter().any(|row| row.id == result.id))
+            .cloned()
+            .collect(),
+    };
+    for (i, row) in rows.iter().enumerate() {
+        let output = row.output_file_name();
+        let mut result = BatchResult {
+            id: row.id.clone(),
+            output: output.clone(),
+            status: BatchStatus::Failed,
+            key: String::new(),
+            error: None,
+        };
+
+        let rendered = row
+            .voice(config)
+            .and_then(|voice| pipeline.chunks(&row.text, &voice))
+            .and_then(|chunks| {
+                result.key = render_key(&chunks);
+                if previous.is_up_to_date(&out_dir, &output, &result.key) {
+                    return Ok(BatchStatus::Skipped);
+                }
+                println!("[{}/{}] {}", i + 1, rows.len(), row.id);
+                render_to_file(&chunks, &out_dir.join(&output), verbose, cache.as_ref())?;
+                Ok(BatchStatus::Rendered)
+            });
+        match rendered {
+            Ok(status) => result.status = status,
+            Err(e) => {
+                eprintln!("[{}/{}] {} failed: {}", i + 1, rows.len(), row.id, e);
+                result.error = Some(e.to_string());
+            }
+        }
+
+        report.record(result);
+        report.save(&out_dir)?;
+    }
+
+    println!(
+        "Rendered {}, skipped {} up to date, failed {}. Report: {}",
+        report.count(BatchStatus::Rendered),
+        report.count(BatchStatus::Skipped),
+        report.count(BatchStatus::Failed),
+        BatchReport::path(&out_dir).display()
+    );
+
+    let failed = report.count(BatchStatus::Failed);
+    if failed > 0 {
+        return Err(format!("{} of {} row(s) failed", failed, rows.len()).into());
+    }
+    Ok(())
+}
+
+fn handle_watch_command(
+    matches: &clap::ArgMatches,
+    config: &Config,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let path = PathBuf::from(matches.get_one::<String>("path").unwrap());
+    if !path.exists() {
+        return Err(format!("No such file or directory: {}", path.display()).into());
+    }
+
+    // Each change is handled by the normal run path, with the voice options
+    // passed through as if they were given to `vp`
+    let mut base_args = vec!["vp".to_string()];
+    for id in ["preset", "narrator", "emotion", "speed", "pitch"] {
+        if let Some(value) = matches.get_one::<String>(id) {
+            base_args.push(format!("--{}={}", id, value));
+        }
+    }
+    if matches.get_flag("verbose") {
+        base_args.push("--verbose".to_string());
+    }
+    let run = |extra: &[&str]| -> Result<(), Box<dyn std::error::Error>> {
+        let mut args = base_args.clone();
+        args.extend(extra.iter().map(|arg| arg.to_string()));
+        run_voicepeak(&build_cli().try_get_matches_from(args)?, config)
+    };
+
+    let Some(out_dir) = matches.get_one::<String>("out-dir").map(PathBuf::from) else {
+        if path.is_dir() {
+            return Err("--out-dir is required to watch a directory".into());
+        }
+
+        let mut tail = FileTail::open(&path)?;
+        println!(
+            "Watching {} for appended text (Ctrl-C to stop)",
+            path.display()
+        );
+        loop {
+            thread::sleep(WATCH_INTERVAL);
+            // A file that is briefly unreadable (e.g. while being replaced)
+            // is tried again on the next poll
+            match tail.read_appended() {
+                Ok(Some(text)) => {
+                    if let Err(e) = run(&["--playback-mode", "stream", "--", text.trim()]) {
+                        eprintln!("Error: {}", e);
+                    }
+                }
+                Ok(None) => {}
+                Err(e) => eprintln!("Error: {}", e),
+            }
+        }
+    };
+
+    let mut watcher = FileWatcher::new(&path);
+    println!(
+        "Watching {} and rendering to {} (Ctrl-C to stop)",
+        path.display(),
+        out_dir.display()
+    );
+    // The first scan only records modification times
+    if let Err(e) = watcher.scan() {
+        eprintln!("Error: {}", e);
+    }
+    loop {
+        thread::sleep(WATCH_INTERVAL);
+        let files = match watcher.scan() {
+            Ok(files) => files,
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                continue;
+            }
+        };
+        for file in files {
+            // notes.md is rendered to notes.md.wav, so it cannot overwrite
+            // the audio of notes.txt
+            let mut output = out_dir.join(watcher.relative_path(&file)).into_os_string();
+            output.push(".wav");
+            let output = PathBuf::from(output);
+            // Existing audio newer than its source was rendered before
+            // watching started
+            let up_to_date = match (std::fs::metadata(&file), std::fs::metadata(&output)) {
+                (Ok(source), Ok(audio)) => {
+                    matches!((source.modified(), audio.modified()), (Ok(s), Ok(a)) if a >= s)
+                }
+                _ => false,
+            };
+            if up_to_date {
+                continue;
+            }
+            if let Some(parent) = output.parent() {
+                if let Err(e) = std::fs::create_dir_all(parent) {
+                    eprintln!("Error: {}: {}", parent.display(), e);
+                    continue;
+                }
+            }
+
+            println!("Rendering {}", file.display());
+            let (Some(file), Some(output)) = (file.to_str(), output.to_str()) else {
+                eprintln!("Error: non UTF-8 path: {}", file.display());
+                continue;
+            };
+            if let Err(e) = run(&["--text", file, "--out", output]) {
+                eprintln!("Error: {}", e);
+            }
+        }
+    }
+}
+
+fn handle_serve_command(
+    matches: &clap::ArgMatches,
+    config: Config,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let pipeline = TextPipeline::new(
+        &config,
+        !matches.get_flag("no-dict"),
+        !matches.get_flag("no-normalize"),
+    )?;
+    let cache = open_cache(&config, matches.get_flag("no-cache"));
+    let compat: Vec<CompatApi> = matches
+        .get_many::<String>("compat")
+        .unwrap_or_default()
+        .filter_map(|name| CompatApi::from_name(name))
+        .collect();
+
+    // VOICEVOX clients expect the engine's port unless told otherwise
+    let listen = match matches.get_one::<String>("listen") {
+        Some(listen) => listen.as_str(),
+        None if compat.contains(&CompatApi::Voicevox) => VOICEVOX_LISTEN_ADDRESS,
+        None => DEFAULT_LISTEN_ADDRESS,
+    };
+
+    let state =
+        ServerState::new(config, pipeline, cache, matches.get_flag("verbose")).compat(compat);
+    serve(listen, state)
+}
+
+#[cfg(unix)]
+fn handle_daemon_command(
+    matches: &clap::ArgMatches,
+    config: Config,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let socket_path = get_socket_path()?;
+    let connect = || {
+        DaemonClient::connect(&socket_path)
+            .map_err(|_| format!("No vp daemon is running on {}", socket_path.display()))
+    };
+    let print_job = |job: &DaemonJob| {
+        let state = job.state.name();
+        match &job.error {
+            Some(error) => println!("{:>4}  {:<9}  {}  ({})", job.id, state, job.summary, error),
+            None => println!("{:>4}  {:<9}  {}", job.id, state, job.summary),
+        }
+    };
+
+    match matches.subcommand() {
+        Some(("list", _)) => {
+            if let DaemonResponse::Jobs { jobs } = connect()?.request(&DaemonRequest::List)? {
+                if jobs.is_empty() {
+                    println!("No jobs");
+                }
+                jobs.iter().for_each(print_job);
+            }
+        }
+        Some(("status", sub)) => {
+            let id = *sub.get_one::<u64>("id").unwrap();
+            if let DaemonResponse::Job { job } =
+                connect()?.request(&DaemonRequest::Status { id })?
+            {
+                print_job(&job);
+            }
+        }
+        Some(("cancel", sub)) => {
+            let id = *sub.get_one::<u64>("id").unwrap();
+            connect()?.request(&DaemonRequest::Cancel { id })?;
+            println!("Cancelled job {}", id);
+        }
+        _ => {
+            let mut config = config;
+            let mut config_modified = config_modified_time();
+            run_daemon(&socket_path, |job| {
+                // Pick up presets and settings edited while the daemon runs
+                let modified = config_modified_time();
+                if modified != config_modified {
+                    config = load_config()
+                        .map_err(|e| format!("Failed to reload the config file: {}", e))?;
+                    config_modified = modified;
+                }
+                run_daemon_job(job, &config)
+            })?
+        }
+    }
+
+    Ok(())
 }
 
-pub fn handle_matches(matches: clap::ArgMatches) -> Result<(), Box<dyn std::error::Error>> {
-    let config = load_config()?;
+#[cfg(not(unix))]
+fn handle_daemon_command(
+    _matches: &clap::ArgMatches,
+    _config: Config,
+) -> Result<(), Box<dyn std::error::Error>> {
+    Err("vp daemon is only supported on Unix systems".into())
+}
 
-    if matches.get_flag("list-narrator") {
-        list_narrator();
-        return Ok(());
+#[cfg(unix)]
+fn config_modified_time() -> Option<std::time::SystemTime> {
+    let path = crate::config::get_config_path().ok()?;
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// Runs a job sent by another `vp` as if it had been run in the client's
+/// directory with the daemon's config. The daemon's own working directory is
+/// restored afterwards.
+#[cfg(unix)]
+fn run_daemon_job(job: &JobRequest, config: &Config) -> Result<(), Box<dyn std::error::Error>> {
+    let previous_dir = std::env::current_dir()?;
+    std::env::set_current_dir(&job.cwd)
+        .map_err(|e| format!("Cannot enter {}: {}", job.cwd.display(), e))?;
+
+    let result = build_cli()
+        .try_get_matches_from(&job.args)
+        .map_err(|e| e.into())
+        .and_then(|matches| match &job.stdin {
+            Some(text) => speak_text(&matches, config, text),
+            None => run_voicepeak(&matches, config),
+        });
+
+    std::env::set_current_dir(&previous_dir)?;
+    result
+}
+
+/// Sends the command to a running daemon instead of running it here.
+/// Returns `None` when it should run directly: there is no daemon, it was
+/// disabled with `--no-daemon`, or the command does not speak text.
+/// With `--bg` the job is only queued; otherwise this waits for it to finish.
+#[cfg(unix)]
+pub fn submit_to_daemon(
+    matches: &clap::ArgMatches,
+) -> Option<Result<(), Box<dyn std::error::Error>>> {
+    let speaks_text = matches.subcommand().is_none()
+        && !matches.get_flag("no-daemon")
+        && !matches.get_flag("follow")
+        && !matches.get_flag("dry-run")
+        && !matches.get_flag("list-narrator")
+        && !matches.get_flag("list-presets")
+        && matches.get_one::<String>("list-emotion").is_none();
+    let has_input = matches.get_one::<String>("text").is_some()
+        || matches.get_one::<String>("file").is_some()
+        || !io::stdin().is_terminal();
+    if !speaks_text || !has_input {
+        return None;
     }
+    let mut client = DaemonClient::connect(&get_socket_path().ok()?).ok()?;
 
-    if let Some(narrator) = matches.get_one::<String>("list-emotion") {
-        list_emotion(narrator);
-        return Ok(());
+    let result = (|| -> Result<(), Box<dyn std::error::Error>> {
+        let stdin = if matches.get_one::<String>("text").is_none()
+            && matches.get_one::<String>("file").is_none()
+        {
+            let mut buffer = String::new();
+            io::stdin().read_to_string(&mut buffer)?;
+            Some(buffer.trim().to_string())
+        } else {
+            None
+        };
+
+        // The daemon must not fork, so --bg only decides whether to wait
+        let background = matches.get_flag("bg");
+        let mut after_separator = false;
+        let args = std::env::args()
+            .filter(|arg| {
+                after_separator |= arg == "--";
+                after_separator || arg != "--bg"
+            })
+            .collect();
+
+        let job = JobRequest {
+            args,
+            cwd: std::env::current_dir()?,
+            stdin,
+        };
+        let DaemonResponse::Job { job } = client.request(&DaemonRequest::Submit {
+            job,
+            wait: !background,
+        })?
+        else {
+            return Err("Unexpected response from the daemon".into());
+        };
+        if background {
+            println!("Queued job {}", job.id);
+            return Ok(());
+        }
+
+        match client.receive()? {
+            DaemonResponse::Job { job } => match job.state {
+                DaemonJobState::Done => Ok(()),
+                DaemonJobState::Cancelled => Err(format!("Job {} was cancelled", job.id).into()),
+                _ => Err(job.error.unwrap_or_else(|| "Job failed".to_string()).into()),
+            },
+            _ => Err("Unexpected response from the daemon".into()),
+        }
+    })();
+    Some(result)
+}
+
+#[cfg(not(unix))]
+pub fn submit_to_daemon(
+    _matches: &clap::ArgMatches,
+) -> Option<Result<(), Box<dyn std::error::Error>>> {
+    None
+}
+
+fn handle_repl_command(
+    matches: &clap::ArgMatches,
+    mut config: Config,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut editor = rustyline::DefaultEditor::new()?;
+    let history_path = get_history_path()?;
+    if let Some(parent) = history_path.parent() {
+        std::fs::create_dir_all(parent)?;
     }
+    // No history yet on the first run
+    let _ = editor.load_history(&history_path);
 
-    if matches.get_flag("list-presets") {
-        list_presets(&config);
-        return Ok(());
+    let mut state = ReplState {
+        preset: matches.get_one::<String>("preset").cloned(),
+        ..ReplState::default()
+    };
+    let verbose = matches.get_flag("verbose");
+    println!("Type :help for commands, :quit or Ctrl-D to exit.");
+
+    loop {
+        let line = match editor.readline("vp> ") {
+            Ok(line) => line,
+            Err(rustyline::error::ReadlineError::Interrupted) => continue,
+            Err(rustyline::error::ReadlineError::Eof) => break,
+            Err(e) => return Err(e.into()),
+        };
+        if !line.trim().is_empty() {
+            let _ = editor.add_history_entry(line.as_str());
+        }
+
+        let command = match parse_repl_line(&line) {
+            Ok(Some(command)) => command,
+            Ok(None) => continue,
+            Err(e) => {
+                eprintln!("{}", e);
+                continue;
+            }
+        };
+        if state.apply(&command) {
+            println!("{}", state.describe());
+            continue;
+        }
+
+        let text = match command {
+            ReplCommand::Speak(text) => text,
+            ReplCommand::Replay => match state.last_text.clone() {
+                Some(text) => text,
+                None => {
+                    eprintln!("Nothing to replay");
+                    continue;
+                }
+            },
+            ReplCommand::Save(name) => {
+                if let Err(e) = save_repl_preset(&name, &state, &mut config) {
+                    eprintln!("Error: {}", e);
+                    continue;
+                }
+                println!("Saved preset: {}", name);
+                state.apply(&ReplCommand::Preset(Some(name)));
+                continue;
+            }
+            ReplCommand::Show => {
+                println!("{}", state.describe());
+                continue;
+            }
+            ReplCommand::Help => {
+                println!("{}", REPL_HELP);
+                continue;
+            }
+            ReplCommand::Quit => break,
+            _ => unreachable!("settings are handled by ReplState::apply"),
+        };
+
+        let mut args = state.args(&text, &get_presets_map(&config));
+        if verbose {
+            args.insert(1, "--verbose".to_string());
+        }
+        let result = build_cli()
+            .try_get_matches_from(args)
+            .map_err(|e| e.into())
+            .and_then(|line_matches| run_voicepeak(&line_matches, &config));
+        if let Err(e) = result {
+            eprintln!("Error: {}", e);
+        }
+        state.last_text = Some(text);
     }
 
-    run_voicepeak(&matches, &config)
+    if let Err(e) = editor.save_history(&history_path) {
+        eprintln!("Warning: failed to save history: {}", e);
+    }
+    Ok(())
+}
+
+/// Saves the voice the REPL would currently speak with as preset `name`.
+fn save_repl_preset(
+    name: &str,
+    state: &ReplState,
+    config: &mut Config,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let presets_map = get_presets_map(config);
+    let matches = build_cli().try_get_matches_from(state.args("", &presets_map))?;
+    let voice = resolve_voice(&matches, config)?;
+    let mut preset = VoicePreset::from_voice_settings(name, &voice)?;
+
+    // Playback settings are not part of the REPL state, so a replaced preset
+    // keeps its own
+    match config.presets.iter_mut().find(|p| p.name == name) {
+        Some(existing) => {
+            preset.device = existing.device.take();
+            preset.volume = existing.volume;
+            preset.playback_rate = existing.playback_rate;
+            *existing = preset.clone();
+        }
+        None => config.presets.push(preset.clone()),
+    }
+    save_preset(&preset)
+}
+
+fn handle_cache_command(
+    matches: &clap::ArgMatches,
+    config: &Config,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let cache = SynthesisCache::open(&config.cache)?;
+
+    match matches.subcommand() {
+        Some(("stats", _)) => print_cache_stats(&cache, &config.cache)?,
+        Some(("clear", _)) => {
+            let removed = cache.clear()?;
+            println!("Removed {} cached file(s)", removed);
+        }
+        Some(("prune", _)) => {
+            let removed = cache.prune()?;
+            println!("Removed {} cached file(s)", removed);
+        }
+        _ => unreachable!("subcommand_required is set"),
+    }
+
+    Ok(())
+}
+
+fn handle_dict_command(matches: &clap::ArgMatches) -> Result<(), Box<dyn std::error::Error>> {
+    let dictionary_path = |sub: &clap::ArgMatches| -> Result<PathBuf, Box<dyn std::error::Error>> {
+        if sub.get_flag("project") {
+            Ok(find_project_dictionary()
+                .unwrap_or_else(|| PathBuf::from(PROJECT_DICTIONARY_FILE_NAME)))
+        } else {
+            get_dictionary_path()
+        }
+    };
+
+    match matches.subcommand() {
+        Some(("add", sub)) => {
+            let path = dictionary_path(sub)?;
+            let mut dictionary = load_dictionary(&path)?;
+            dictionary.add(DictionaryEntry {
+                pattern: sub.get_one::<String>("pattern").unwrap().clone(),
+                replacement: sub.get_one::<String>("replacement").unwrap().clone(),
+                regex: sub.get_flag("regex"),
+                narrator: sub.get_one::<String>("narrator").cloned(),
+            })?;
+            save_dictionary(&path, &dictionary)?;
+            println!("Saved to: {}", path.display());
+        }
+        Some(("remove", sub)) => {
+            let path = dictionary_path(sub)?;
+            let mut dictionary = load_dictionary(&path)?;
+            let pattern = sub.get_one::<String>("pattern").unwrap();
+            let removed = dictionary.remove(
+                pattern,
+                sub.get_one::<String>("narrator").map(|s| s.as_str()),
+            );
+            if removed == 0 {
+                return Err(format!("No dictionary entry for: {}", pattern).into());
+            }
+            save_dictionary(&path, &dictionary)?;
+            println!("Removed {} entry(s) from: {}", removed, path.display());
+        }
+        Some(("list", sub)) => {
+            if sub.get_flag("project") {
+                let path = dictionary_path(sub)?;
+                list_dictionary(&load_dictionary(&path)?, &path);
+            } else {
+                if let Some(project_path) = find_project_dictionary() {
+                    list_dictionary(&load_dictionary(&project_path)?, &project_path);
+                    println!();
+                }
+                let path = get_dictionary_path()?;
+                list_dictionary(&load_dictionary(&path)?, &path);
+            }
+        }
+        Some(("test", sub)) => {
+            let text = sub.get_one::<String>("text").unwrap();
+            let narrator = sub
+                .get_one::<String>("narrator")
+                .map(|s| s.as_str())
+                .unwrap_or_default();
+            println!("{}", load_dictionaries()?.apply(text, narrator)?);
+        }
+        _ => unreachable!("subcommand_required is set"),
+    }
+
+    Ok(())
 }
 
 fn run_voicepeak(
     matches: &clap::ArgMatches,
     config: &Config,
 ) -> Result<(), Box<dyn std::error::Error>> {
+    if matches.get_flag("follow") {
+        return follow_stdin(matches, config);
+    }
+
     let input_text = if let Some(text) = matches.get_one::<String>("text") {
         text.clone()
     } else if let Some(file_path) = matches.get_one::<String>("file") {
@@ -152,97 +1346,229 @@ fn run_voicepeak(
         return Err("Either text argument, --text file, or pipe input must be specified".into());
     };
 
-    let presets_map = get_presets_map(config);
+    speak_text(matches, config, &input_text)
+}
 
-    let (narrator, emotion, preset_pitch, preset_speed) =
-        if let Some(preset_name) = matches.get_one::<String>("preset") {
-            // Explicit preset specified via -p option
-            let preset = presets_map
-                .get(preset_name)
-                .ok_or_else(|| format!("Unknown preset: {}", preset_name))?;
-            (
-                preset.narrator.clone(),
-                preset.get_emotion_string(),
-                preset.pitch,
-                preset.speed,
-            )
-        } else if let Some(default_preset_name) = &config.default_preset {
-            // No preset specified, but default_preset exists in config
-            if let Some(default_preset) = presets_map.get(default_preset_name) {
-                // Use default preset, but allow individual overrides
-                let narrator = matches
-                    .get_one::<String>("narrator")
-                    .cloned()
-                    .unwrap_or_else(|| default_preset.narrator.clone());
-                let emotion = matches
-                    .get_one::<String>("emotion")
-                    .cloned()
-                    .unwrap_or_else(|| default_preset.get_emotion_string());
-                let preset_pitch = if matches.get_one::<String>("emotion").is_some() {
-                    None // If emotion is overridden, don't use preset pitch
-                } else {
-                    default_preset.pitch
-                };
-                let preset_speed = default_preset.speed;
-                (narrator, emotion, preset_pitch, preset_speed)
-            } else {
-                // Default preset not found, fallback to manual settings
-                let narrator = matches
-                    .get_one::<String>("narrator")
-                    .cloned()
-                    .ok_or("No narrator specified. Use --narrator option or configure a preset.")?;
-                let emotion = matches
-                    .get_one::<String>("emotion")
-                    .cloned()
-                    .unwrap_or_default();
-                (narrator, emotion, None, None)
-            }
-        } else {
-            // No preset and no default_preset, use manual settings only
-            let narrator = matches
-                .get_one::<String>("narrator")
-                .cloned()
-                .ok_or("No narrator specified. Use --narrator option or configure a preset.")?;
-            let emotion = matches
-                .get_one::<String>("emotion")
-                .cloned()
-                .unwrap_or_default();
-            (narrator, emotion, None, None)
-        };
+/// Speaks lines from stdin as they arrive, one `speak_text` call per line.
+fn follow_stdin(
+    matches: &clap::ArgMatches,
+    config: &Config,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let patterns = |id: &str| -> Vec<String> {
+        matches
+            .get_many::<String>(id)
+            .map(|values| values.cloned().collect())
+            .unwrap_or_default()
+    };
+    let filter = LineFilter::new(&patterns("include"), &patterns("exclude"))?;
+    let max_backlog = matches.get_one::<u32>("max-backlog").copied().unwrap_or(10);
+    let min_interval = matches
+        .get_one::<String>("min-interval")
+        .map(|value| parse_duration(value))
+        .transpose()?;
 
-    let speed = matches
-        .get_one::<String>("speed")
-        .cloned()
-        .or_else(|| preset_speed.map(|s| s.to_string()));
-    let pitch = matches
-        .get_one::<String>("pitch")
-        .cloned()
-        .or_else(|| preset_pitch.map(|p| p.to_string()));
-    let should_play = matches.get_one::<String>("out").is_none();
+    let lines = LineQueue::spawn(
+        io::BufReader::new(io::stdin()),
+        filter,
+        max_backlog as usize,
+    );
+    let mut last_start: Option<std::time::Instant> = None;
+
+    for (line, dropped) in lines {
+        if dropped > 0 {
+            eprintln!("Skipped {} line(s) to catch up", dropped);
+        }
+        if let (Some(interval), Some(start)) = (min_interval, last_start) {
+            thread::sleep(interval.saturating_sub(start.elapsed()));
+        }
+        last_start = Some(std::time::Instant::now());
+
+        // One bad line should not end the stream
+        if let Err(e) = speak_text(matches, config, &line) {
+            eprintln!("Error: {}", e);
+        }
+    }
+
+    Ok(())
+}
+
+fn speak_text(
+    matches: &clap::ArgMatches,
+    config: &Config,
+    input_text: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let presets_map = get_presets_map(config);
+    let script_mode = matches.get_flag("script");
+    let markup_mode = matches.get_flag("markup");
+    let dry_run = matches.get_flag("dry-run");
+    let input_format = match matches.get_one::<String>("input-format") {
+        Some(format) => format.as_str(),
+        None if markup_mode || matches.get_flag("ssml") || script_mode => "text",
+        None => match matches.get_one::<String>("file").map(Path::new) {
+            Some(path) if is_markdown_path(path) => "markdown",
+            Some(path) if is_html_path(path) => "html",
+            _ => "text",
+        },
+    };
+    let ssml_mode = !script_mode
+        && input_format == "text"
+        && (matches.get_flag("ssml")
+            || (!markup_mode
+                && !matches.contains_id("input-format")
+                && looks_like_ssml(input_text)));
+
+    let voice = match resolve_voice(matches, config) {
+        Ok(voice) => voice,
+        // Dialogue scripts name a speaker per line, markup and SSML can pick
+        // a voice per segment, and a dry run never synthesizes, so a global narrator
+        // is optional. Segments are checked for a narrator once parsed.
+        Err(_)
+            if (script_mode || markup_mode || ssml_mode || dry_run)
+                && matches.get_one::<String>("preset").is_none() =>
+        {
+            VoiceSettings::default()
+        }
+        Err(e) => return Err(e),
+    };
+    let active_preset = matches
+        .get_one::<String>("preset")
+        .or(config.default_preset.as_ref())
+        .and_then(|name| presets_map.get(name));
+    let playback = PlaybackOptions {
+        device: matches
+            .get_one::<String>("device")
+            .cloned()
+            .or_else(|| active_preset.and_then(|p| p.device.clone())),
+        volume: matches
+            .get_one::<u32>("volume")
+            .copied()
+            .or_else(|| active_preset.and_then(|p| p.volume)),
+        rate: matches
+            .get_one::<f64>("playback-rate")
+            .copied()
+            .or_else(|| active_preset.and_then(|p| p.playback_rate)),
+    };
     let output_path = matches.get_one::<String>("out").map(PathBuf::from);
+    let split_output = matches.get_one::<String>("split-output").map(PathBuf::from);
+    let should_play = output_path.is_none() && split_output.is_none();
+    let subtitles = match matches.get_one::<String>("subtitles").map(PathBuf::from) {
+        Some(path) => {
+            // Validate the extension before spending time on synthesis
+            SubtitleFormat::from_path(&path)?;
+            let unit = match matches
+                .get_one::<String>("subtitle-unit")
+                .map(|s| s.as_str())
+            {
+                Some("sentence") => SubtitleUnit::Sentence,
+                _ => SubtitleUnit::Chunk,
+            };
+            Some((path, unit))
+        }
+        None => None,
+    };
+    let title = matches.get_one::<String>("title");
+    let artist = matches.get_one::<String>("artist");
+    let metadata = if matches.get_flag("tag") || title.is_some() || artist.is_some() {
+        let mut comment = Vec::new();
+        if let Some(preset) = active_preset {
+            comment.push(format!("preset={}", preset.name));
+        }
+        if !voice.emotion.is_empty() {
+            comment.push(format!("emotion={}", voice.emotion));
+        }
+        Some(AudioMetadata {
+            title: Some(title.cloned().unwrap_or_else(|| default_title(input_text))),
+            artist: artist
+                .cloned()
+                .or_else(|| (!voice.narrator.is_empty()).then(|| voice.narrator.clone())),
+            comment: (!comment.is_empty()).then(|| comment.join("; ")),
+            text: Some(input_text.trim().to_string()),
+        })
+    } else {
+        None
+    };
     let strict_length = matches.get_flag("strict-length");
+    let mut splitter = TextSplitter::from_config(&config.splitter);
+    if let Some(max_chars) = matches.get_one::<u32>("max-chars") {
+        splitter = splitter.max_chars(*max_chars as usize);
+    }
+    if let Some(terminators) = matches.get_one::<String>("sentence-terminators") {
+        splitter = splitter.sentence_terminators(terminators);
+    }
+    if let Some(breaks) = matches.get_one::<String>("soft-breaks") {
+        splitter = splitter.soft_breaks(breaks);
+    }
     let playback_mode = matches.get_one::<String>("playback-mode").unwrap();
     let verbose = matches.get_flag("verbose");
 
-    if strict_length && !check_text_length(&input_text) {
-        return Err(format!(
-            "Input text is too long ({} characters). Maximum allowed is {} characters.\nUse without --strict-length to enable automatic splitting.",
-            input_text.chars().count(),
-            MAX_CHARS
-        ).into());
+    let mut segments = if script_mode {
+        let turn_gap = parse_duration(matches.get_one::<String>("turn-gap").unwrap())?;
+        parse_dialogue(input_text, &voice, &presets_map, &config.speakers, turn_gap)?
+    } else if input_format == "markdown" {
+        parse_markdown(input_text, &voice, &config.markdown)
+    } else if input_format == "html" {
+        parse_html(input_text, &voice)
+    } else if ssml_mode {
+        parse_ssml(input_text, &voice, &presets_map)?
+    } else if markup_mode {
+        parse_markup(input_text, &voice, &presets_map)?
+    } else {
+        vec![Segment::new(input_text, &voice)]
+    };
+    if !dry_run {
+        if let Some(segment) = segments.iter().find(|s| s.voice.narrator.is_empty()) {
+            return Err(format!(
+                "No narrator for \"{}\": set a preset or narrator, or configure a default preset",
+                default_title(&segment.text)
+            )
+            .into());
+        }
+    }
+
+    // Normalization first, so dictionary output is read as written
+    if !matches.get_flag("no-normalize") {
+        for segment in &mut segments {
+            segment.text = normalize_text(&segment.text, &config.normalize);
+        }
     }
 
-    let text_chunks = split_text(&input_text);
+    if !matches.get_flag("no-dict") {
+        let dictionary = load_dictionaries()?;
+        for segment in &mut segments {
+            segment.text = dictionary.apply(&segment.text, &segment.voice.narrator)?;
+        }
+    }
 
-    if text_chunks.len() > 1 {
-        println!(
-            "Text is too long, splitting into {} parts...",
-            text_chunks.len()
-        );
+    if strict_length {
+        if let Some(segment) = segments.iter().find(|s| !splitter.check_length(&s.text)) {
+            return Err(format!(
+                "Input text is too long ({} characters). Maximum allowed is {} characters.\nUse without --strict-length to enable automatic splitting.",
+                segment.text.chars().count(),
+                splitter.get_max_chars()
+            ).into());
+        }
+    }
+
+    let chunks_with_boundaries = chunk_segments_with_boundaries(&segments, &splitter);
+
+    if chunks_with_boundaries.is_empty() {
+        return Err("Input text is empty".into());
+    }
+    if dry_run {
+        return print_dry_run(&chunks_with_boundaries, matches.get_flag("json"));
+    }
+
+    let chunks: Vec<Segment> = chunks_with_boundaries
+        .into_iter()
+        .map(|(chunk, _)| chunk)
+        .collect();
+    let cache = open_cache(config, matches.get_flag("no-cache"));
+    if chunks.len() > segments.len() {
+        println!("Text is too long, splitting into {} parts...", chunks.len());
     }
 
     // Check ffmpeg availability for batch mode
-    if (playback_mode == "batch" || (!should_play && text_chunks.len() > 1))
+    if ((should_play && playback_mode == "batch") || (output_path.is_some() && chunks.len() > 1))
         && !check_ffmpeg_available()
     {
         return Err(
@@ -287,68 +1613,37 @@ fn run_voicepeak(
         // Auto-play mode
         if playback_mode == "sequential" {
             // Sequential mode: generate and play one by one
-            for (i, chunk) in text_chunks.iter().enumerate() {
-                if text_chunks.len() > 1 {
-                    println!("Playing part {}/{}", i + 1, text_chunks.len());
+            for (i, chunk) in chunks.iter().enumerate() {
+                if chunks.len() > 1 {
+                    println!("Playing part {}/{}", i + 1, chunks.len());
                 }
 
                 let temp_path = create_temp_audio_file()?;
-
-                let mut cmd = VoicepeakCommand::new()
-                    .text(chunk)
-                    .narrator(&narrator)
-                    .emotion(&emotion)
-                    .output(&temp_path);
-
-                if let Some(speed) = &speed {
-                    cmd = cmd.speed(speed);
-                }
-                if let Some(pitch) = &pitch {
-                    cmd = cmd.pitch(pitch);
+                synthesize_chunk(chunk, &temp_path, verbose, cache.as_ref())?;
+                if let Some(pause) = chunk.pause_before {
+                    thread::sleep(pause);
                 }
-
-                cmd.execute_with_verbose(verbose)?;
-                play_audio_and_cleanup(&temp_path)?;
+                play_audio_and_cleanup_with_options(&temp_path, &playback)?;
             }
+        } else if playback_mode == "stream" {
+            // Stream mode: generate the next chunk while the current one plays
+            play_streaming(&chunks, verbose, &playback, cache.as_ref())?;
         } else {
             // Batch mode: generate all, merge, then play
-            let mut temp_files = Vec::new();
-
-            for (i, chunk) in text_chunks.iter().enumerate() {
-                if text_chunks.len() > 1 {
-                    println!("Generating part {}/{}", i + 1, text_chunks.len());
-                }
-
-                let temp_path = create_temp_audio_file()?;
-
-                let mut cmd = VoicepeakCommand::new()
-                    .text(chunk)
-                    .narrator(&narrator)
-                    .emotion(&emotion)
-                    .output(&temp_path);
-
-                if let Some(speed) = &speed {
-                    cmd = cmd.speed(speed);
-                }
-                if let Some(pitch) = &pitch {
-                    cmd = cmd.pitch(pitch);
-                }
-
-                cmd.execute_with_verbose(verbose)?;
-                temp_files.push(temp_path);
-            }
+            let temp_files = generate_chunks(&chunks, verbose, cache.as_ref())?;
 
             // Merge and play
             let final_temp = create_temp_audio_file()?;
             let temp_paths: Vec<&std::path::Path> =
                 temp_files.iter().map(|p| p.as_path()).collect();
+            let gaps = segment_gaps(&chunks);
 
-            if text_chunks.len() > 1 {
+            if chunks.len() > 1 {
                 println!("Merging audio files...");
-                merge_audio_files(&temp_paths, &final_temp)?;
+                merge_audio_files_with_gaps(&temp_paths, &gaps, &final_temp)?;
                 println!("Merge complete. Playing audio...");
             } else {
-                merge_audio_files(&temp_paths, &final_temp)?;
+                merge_audio_files_with_gaps(&temp_paths, &gaps, &final_temp)?;
             }
 
             // Cleanup individual temp files
@@ -356,54 +1651,285 @@ fn run_voicepeak(
                 let _ = std::fs::remove_file(temp_file);
             }
 
-            play_audio_and_cleanup(&final_temp)?;
+            play_audio_and_cleanup_with_options(&final_temp, &playback)?;
         }
-    } else if let Some(output_path) = output_path {
-        // File output mode
-        let mut temp_files = Vec::new();
+    } else {
+        // File output mode: chunks are kept in a job directory until the
+        // outputs are written, so a failed render can be resumed
+        let job = Job::prepare(
+            &chunks,
+            output_path.as_deref(),
+            split_output.as_deref(),
+            subtitles
+                .as_ref()
+                .map(|(path, unit)| (path.as_path(), *unit)),
+            metadata.as_ref(),
+            matches.get_flag("resume"),
+        )?;
+        render_job(job, verbose, cache.as_ref(), &splitter)?;
+    }
 
-        for (i, chunk) in text_chunks.iter().enumerate() {
-            if text_chunks.len() > 1 {
-                println!("Generating part {}/{}", i + 1, text_chunks.len());
-            }
+    Ok(())
+}
 
-            let temp_path = create_temp_audio_file()?;
+fn resolve_voice(
+    matches: &clap::ArgMatches,
+    config: &Config,
+) -> Result<VoiceSettings, Box<dyn std::error::Error>> {
+    let number = |name: &str| -> Result<Option<i32>, Box<dyn std::error::Error>> {
+        matches
+            .get_one::<String>(name)
+            .map(|value| {
+                value
+                    .parse::<i32>()
+                    .map_err(|_| format!("Invalid {}: {}", name, value).into())
+            })
+            .transpose()
+    };
 
-            let mut cmd = VoicepeakCommand::new()
-                .text(chunk)
-                .narrator(&narrator)
-                .emotion(&emotion)
-                .output(&temp_path);
+    VoiceOptions {
+        preset: matches.get_one::<String>("preset").cloned(),
+        narrator: matches.get_one::<String>("narrator").cloned(),
+        emotion: matches.get_one::<String>("emotion").cloned(),
+        pitch: number("pitch")?,
+        speed: number("speed")?,
+    }
+    .resolve(config)
+}
 
-            if let Some(speed) = &speed {
-                cmd = cmd.speed(speed);
-            }
-            if let Some(pitch) = &pitch {
-                cmd = cmd.pitch(pitch);
-            }
+#[derive(Serialize)]
+struct DryRunChunk<'a> {
+    index: usize,
+    chars: usize,
+    boundary: Boundary,
+    narrator: &'a str,
+    emotion: &'a str,
+    /// Silence before the chunk when merged, in milliseconds.
+    gap_ms: u128,
+    text: &'a str,
+}
+
+fn print_dry_run(
+    chunks: &[(Segment, Boundary)],
+    json: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let segments: Vec<Segment> = chunks.iter().map(|(chunk, _)| chunk.clone()).collect();
+    let gaps = segment_gaps(&segments);
+    let entries: Vec<DryRunChunk> = chunks
+        .iter()
+        .zip(&gaps)
+        .enumerate()
+        .map(|(i, ((chunk, boundary), gap))| DryRunChunk {
+            index: i + 1,
+            chars: chunk.text.chars().count(),
+            boundary: *boundary,
+            narrator: &chunk.voice.narrator,
+            emotion: &chunk.voice.emotion,
+            gap_ms: gap.as_millis(),
+            text: &chunk.text,
+        })
+        .collect();
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&entries)?);
+        return Ok(());
+    }
+
+    for entry in &entries {
+        let mut details = vec![
+            format!("{} chars", entry.chars),
+            entry.boundary.description().to_string(),
+        ];
+        if !entry.narrator.is_empty() {
+            details.push(entry.narrator.to_string());
+        }
+        if entry.index > 1 {
+            details.push(format!("gap {}ms", entry.gap_ms));
+        }
+        println!("[{}] {}", entry.index, details.join(", "));
+        println!("    {}", entry.text);
+    }
+    println!("{} chunk(s)", entries.len());
+
+    Ok(())
+}
+
+fn default_title(text: &str) -> String {
+    const MAX_TITLE_CHARS: usize = 64;
+
+    let first_line = text.trim().lines().next().unwrap_or_default();
+    if first_line.chars().count() > MAX_TITLE_CHARS {
+        let truncated: String = first_line.chars().take(MAX_TITLE_CHARS).collect();
+        format!("{}…", truncated)
+    } else {
+        first_line.to_string()
+    }
+}
+
+fn open_cache(config: &Config, disabled: bool) -> Option<SynthesisCache> {
+    if disabled || !config.cache.enabled {
+        return None;
+    }
+
+    // A broken cache should never stop synthesis
+    let cache = match SynthesisCache::open(&config.cache) {
+        Ok(cache) => cache,
+        Err(e) => {
+            eprintln!("Warning: synthesis cache disabled: {}", e);
+            return None;
+        }
+    };
+    if let Err(e) = cache.prune_if_due() {
+        eprintln!("Warning: failed to prune synthesis cache: {}", e);
+    }
+    Some(cache)
+}
+
+/// Renders the chunks of `job` that are not on disk yet, then writes its
+/// outputs and removes the job directory.
+fn render_job(
+    job: Job,
+    verbose: bool,
+    cache: Option<&SynthesisCache>,
+    splitter: &TextSplitter,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let chunks = &job.state.chunks;
+    let rendered = job.rendered_count();
+    if rendered > 0 {
+        println!(
+            "Resuming job {}: {}/{} parts already rendered",
+            job.id(),
+            rendered,
+            chunks.len()
+        );
+    }
 
-            cmd.execute_with_verbose(verbose)?;
-            temp_files.push(temp_path);
+    for (i, chunk) in chunks.iter().enumerate() {
+        let chunk_path = job.chunk_path(i);
+        if chunk_path.is_file() {
+            continue;
+        }
+        if chunks.len() > 1 {
+            println!("Generating part {}/{}", i + 1, chunks.len());
         }
 
+        let partial_path = job.partial_chunk_path(i);
+        synthesize_chunk(chunk, &partial_path, verbose, cache)
+            .and_then(|_| Ok(std::fs::rename(&partial_path, &chunk_path)?))
+            .map_err(|e| {
+                let _ = std::fs::remove_file(&partial_path);
+                format!(
+                    "{}\nRendered parts were kept. Resume with: vp resume {}",
+                    e,
+                    job.id()
+                )
+            })?;
+    }
+
+    let files = job.chunk_files();
+    let state = &job.state;
+
+    if let Some(output_path) = &state.output {
         // Merge to final output
-        let temp_paths: Vec<&std::path::Path> = temp_files.iter().map(|p| p.as_path()).collect();
+        let paths: Vec<&std::path::Path> = files.iter().map(|p| p.as_path()).collect();
+        let gaps = segment_gaps(chunks);
 
-        if text_chunks.len() > 1 {
+        if chunks.len() > 1 {
             println!("Merging audio files...");
-            merge_audio_files(&temp_paths, &output_path)?;
+            merge_audio_files_with_gaps(&paths, &gaps, output_path)?;
             println!("Merge complete.");
         } else {
-            merge_audio_files(&temp_paths, &output_path)?;
+            merge_audio_files_with_gaps(&paths, &gaps, output_path)?;
+        }
+
+        if let Some(subtitles_path) = &state.subtitles {
+            let durations = files
+                .iter()
+                .map(|p| wav_duration(p))
+                .collect::<Result<Vec<_>, _>>()?;
+            let texts: Vec<String> = chunks.iter().map(|c| c.text.clone()).collect();
+            let cues = build_cues(&texts, &durations, &gaps, state.subtitle_unit, splitter);
+            write_subtitles(subtitles_path, &cues)?;
+            println!("Subtitles saved to: {}", subtitles_path.display());
         }
 
-        // Cleanup temp files
-        for temp_file in temp_files {
-            let _ = std::fs::remove_file(temp_file);
+        if let Some(metadata) = &state.metadata {
+            write_metadata(output_path, metadata)?;
         }
 
         println!("Audio saved to: {}", output_path.display());
     }
 
-    Ok(())
+    if let Some(split_dir) = &state.split_output {
+        write_split_output(split_dir, chunks, &files, state.metadata.as_ref())?;
+        println!(
+            "{} clips and {} saved to: {}",
+            files.len(),
+            MANIFEST_FILE_NAME,
+            split_dir.display()
+        );
+    }
+
+    job.remove()
+}
+
+fn play_streaming(
+    chunks: &[Segment],
+    verbose: bool,
+    playback: &PlaybackOptions,
+    cache: Option<&SynthesisCache>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    // A rendezvous channel keeps the generator exactly one chunk ahead of playback
+    let (tx, rx) = mpsc::sync_channel::<Result<PathBuf, String>>(0);
+    let generator_chunks = chunks.to_vec();
+    let cache = cache.cloned();
+
+    let generator = thread::spawn(move || {
+        for chunk in generator_chunks {
+            let result = create_temp_audio_file()
+                .and_then(|temp_path| {
+                    synthesize_chunk(&chunk, &temp_path, verbose, cache.as_ref())
+                        .map(|_| temp_path.clone())
+                        .inspect_err(|_| {
+                            let _ = std::fs::remove_file(&temp_path);
+                        })
+                })
+                .map_err(|e| e.to_string());
+            let failed = result.is_err();
+
+            if let Err(mpsc::SendError(Ok(temp_path))) = tx.send(result) {
+                // Playback stopped early; nobody will consume this chunk
+                let _ = std::fs::remove_file(temp_path);
+                break;
+            }
+            if failed {
+                break;
+            }
+        }
+    });
+
+    let played = (|| -> Result<(), Box<dyn std::error::Error>> {
+        for (i, (result, chunk)) in rx.iter().zip(chunks).enumerate() {
+            let temp_path = result?;
+            if chunks.len() > 1 {
+                println!("Playing part {}/{}", i + 1, chunks.len());
+            }
+            if let Some(pause) = chunk.pause_before {
+                thread::sleep(pause);
+            }
+            if let Err(e) = play_audio_and_cleanup_with_options(&temp_path, playback) {
+                let _ = std::fs::remove_file(&temp_path);
+                return Err(e);
+            }
+        }
+        Ok(())
+    })();
+
+    // Dropping the receiver makes the generator stop after the chunk it is
+    // working on and remove that chunk's file, so nothing is left behind
+    drop(rx);
+    generator
+        .join()
+        .map_err(|_| "Audio generation thread panicked")?;
+    played
 }
@@ -3,6 +3,12 @@ use std::collections::HashMap;
 use std::fs;
 use std::path::PathBuf;
 
+use crate::cache::CacheConfig;
+use crate::markdown::MarkdownConfig;
+use crate::normalize::NormalizeConfig;
+use crate::text_splitter::SplitterConfig;
+use crate::voicepeak::VoiceSettings;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EmotionParam {
     pub name: String,
@@ -16,6 +22,9 @@ pub struct VoicePreset {
     pub emotions: Vec<EmotionParam>,
     pub pitch: Option<i32>,
     pub speed: Option<i32>,
+    pub device: Option<String>,
+    pub volume: Option<u32>,
+    pub playback_rate: Option<f64>,
 }
 
 impl VoicePreset {
@@ -30,6 +39,51 @@ impl VoicePreset {
                 .join(",")
         }
     }
+
+    /// Creates a preset from resolved voice settings. The emotion must be in
+    /// `name=value,...` form.
+    pub fn from_voice_settings(
+        name: &str,
+        voice: &VoiceSettings,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut emotions = Vec::new();
+        for param in voice.emotion.split(',').filter(|p| !p.trim().is_empty()) {
+            let (emotion, value) = param
+                .split_once('=')
+                .ok_or_else(|| format!("Invalid emotion parameter: {}", param))?;
+            let value = value
+                .trim()
+                .parse()
+                .map_err(|_| format!("Invalid emotion value: {}", param))?;
+            emotions.push(EmotionParam::new(emotion.trim(), value));
+        }
+        let number = |value: &Option<String>| -> Result<Option<i32>, String> {
+            value
+                .as_ref()
+                .map(|v| v.parse().map_err(|_| format!("Invalid number: {}", v)))
+                .transpose()
+        };
+
+        Ok(Self {
+            name: name.to_string(),
+            narrator: voice.narrator.clone(),
+            emotions,
+            pitch: number(&voice.pitch)?,
+            speed: number(&voice.speed)?,
+            device: None,
+            volume: None,
+            playback_rate: None,
+        })
+    }
+
+    pub fn voice_settings(&self) -> VoiceSettings {
+        VoiceSettings {
+            narrator: self.narrator.clone(),
+            emotion: self.get_emotion_string(),
+            speed: self.speed.map(|s| s.to_string()),
+            pitch: self.pitch.map(|p| p.to_string()),
+        }
+    }
 }
 
 impl EmotionParam {
@@ -41,10 +95,86 @@ impl EmotionParam {
     }
 }
 
+/// Voice given by preset name and individual overrides, as in batch rows and
+/// API requests.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct VoiceOptions {
+    /// Preset name, or a speaker label mapped in `[speakers]`.
+    #[serde(default)]
+    pub preset: Option<String>,
+    #[serde(default)]
+    pub narrator: Option<String>,
+    #[serde(default)]
+    pub emotion: Option<String>,
+    #[serde(default)]
+    pub pitch: Option<i32>,
+    #[serde(default)]
+    pub speed: Option<i32>,
+}
+
+impl VoiceOptions {
+    /// Resolves the preset (or the configured default preset) with the
+    /// narrator, emotion, pitch and speed overriding it. This is the one
+    /// resolution used by `vp`, batch rows and the HTTP API.
+    pub fn resolve(&self, config: &Config) -> Result<VoiceSettings, Box<dyn std::error::Error>> {
+        let presets = get_presets_map(config);
+        let preset = match &self.preset {
+            Some(name) => {
+                let preset_name = config.speakers.get(name).unwrap_or(name);
+                Some(
+                    presets
+                        .get(preset_name)
+                        .ok_or_else(|| format!("Unknown preset: {}", name))?,
+                )
+            }
+            None => config
+                .default_preset
+                .as_ref()
+                .and_then(|name| presets.get(name)),
+        };
+
+        let mut voice = preset.map(|p| p.voice_settings()).unwrap_or_default();
+        if let Some(narrator) = &self.narrator {
+            voice.narrator = narrator.clone();
+        }
+        if let Some(emotion) = &self.emotion {
+            voice.emotion = emotion.clone();
+            // The default preset's pitch is tuned for its own emotion
+            if self.preset.is_none() {
+                voice.pitch = None;
+            }
+        }
+        if let Some(pitch) = self.pitch {
+            voice.pitch = Some(pitch.to_string());
+        }
+        if let Some(speed) = self.speed {
+            voice.speed = Some(speed.to_string());
+        }
+
+        if voice.narrator.is_empty() {
+            return Err(
+                "No narrator: set a preset or narrator, or configure a default preset".into(),
+            );
+        }
+        Ok(voice)
+    }
+}
+
 #[derive(Debug, Default, Serialize, Deserialize)]
 pub struct Config {
     pub default_preset: Option<String>,
     pub presets: Vec<VoicePreset>,
+    /// Dialogue script speaker labels mapped to preset names.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub speakers: HashMap<String, String>,
+    #[serde(default)]
+    pub normalize: NormalizeConfig,
+    #[serde(default)]
+    pub markdown: MarkdownConfig,
+    #[serde(default)]
+    pub splitter: SplitterConfig,
+    #[serde(default)]
+    pub cache: CacheConfig,
 }
 
 pub fn get_config_path() -> Result<PathBuf, Box<dyn std::error::Error>> {
@@ -79,6 +209,35 @@ pub fn save_config(config: &Config) -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
+/// Saves `preset` to the config file, replacing the preset with the same
+/// name. Only that `[[presets]]` entry is rewritten, so comments and the rest
+/// of the file are kept as written.
+pub fn save_preset(preset: &VoicePreset) -> Result<(), Box<dyn std::error::Error>> {
+    let config_path = get_config_path()?;
+    let content = fs::read_to_string(&config_path)?;
+    let mut document: toml_edit::DocumentMut = content.parse()?;
+
+    let mut table = toml_edit::ser::to_document(preset)?.as_table().clone();
+    let presets = document
+        .entry("presets")
+        .or_insert(toml_edit::Item::ArrayOfTables(Default::default()))
+        .as_array_of_tables_mut()
+        .ok_or("`presets` in the config file must be written as [[presets]] tables")?;
+    let existing = presets
+        .iter_mut()
+        .find(|t| t.get("name").and_then(|name| name.as_str()) == Some(preset.name.as_str()));
+    match existing {
+        Some(existing) => {
+            *table.decor_mut() = existing.decor().clone();
+            *existing = table;
+        }
+        None => presets.push(table),
+    }
+
+    fs::write(&config_path, document.to_string())?;
+    Ok(())
+}
+
 pub fn get_presets_map(config: &Config) -> HashMap<String, VoicePreset> {
     config
         .presets
@@ -108,9 +267,16 @@ pub fn list_presets(config: &Config) {
             .speed
             .map(|s| format!(", speed={}", s))
             .unwrap_or_default();
+        let playback_display = format_playback_settings(preset);
         println!(
-            "  {} - {} ({}{}{}){}",
-            preset.name, preset.narrator, emotion_display, pitch_display, speed_display, marker
+            "  {} - {} ({}{}{}){}{}",
+            preset.name,
+            preset.narrator,
+            emotion_display,
+            pitch_display,
+            speed_display,
+            playback_display,
+            marker
         );
     }
 
@@ -120,3 +286,22 @@ pub fn list_presets(config: &Config) {
         println!("\nNo default preset set");
     }
 }
+
+fn format_playback_settings(preset: &VoicePreset) -> String {
+    let mut settings = Vec::new();
+    if let Some(ref device) = preset.device {
+        settings.push(format!("device={}", device));
+    }
+    if let Some(volume) = preset.volume {
+        settings.push(format!("volume={}", volume));
+    }
+    if let Some(rate) = preset.playback_rate {
+        settings.push(format!("rate={}", rate));
+    }
+
+    if settings.is_empty() {
+        String::new()
+    } else {
+        format!(" [{}]", settings.join(", "))
+    }
+}
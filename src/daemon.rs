@@ -0,0 +1,387 @@
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Finished jobs kept for `status` and `list`.
+const FINISHED_JOB_HISTORY: usize = 100;
+
+const SUMMARY_CHARS: usize = 60;
+
+/// A `vp` invocation to run in the daemon.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JobRequest {
+    /// Command-line arguments, including the program name.
+    pub args: Vec<String>,
+    /// Working directory relative paths and project dictionaries are
+    /// resolved from.
+    pub cwd: PathBuf,
+    /// Piped input, when neither text nor `--text` is given.
+    #[serde(default)]
+    pub stdin: Option<String>,
+}
+
+/// One JSON message per line from client to daemon.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum DaemonRequest {
+    /// Queues a job. With `wait`, a second response is sent when it finishes.
+    Submit {
+        job: JobRequest,
+        #[serde(default)]
+        wait: bool,
+    },
+    Status {
+        id: u64,
+    },
+    Cancel {
+        id: u64,
+    },
+    List,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DaemonJobState {
+    Queued,
+    Running,
+    Done,
+    Failed,
+    Cancelled,
+}
+
+impl DaemonJobState {
+    pub fn name(self) -> &'static str {
+        match self {
+            DaemonJobState::Queued => "queued",
+            DaemonJobState::Running => "running",
+            DaemonJobState::Done => "done",
+            DaemonJobState::Failed => "failed",
+            DaemonJobState::Cancelled => "cancelled",
+        }
+    }
+
+    pub fn is_finished(self) -> bool {
+        matches!(
+            self,
+            DaemonJobState::Done | DaemonJobState::Failed | DaemonJobState::Cancelled
+        )
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DaemonJob {
+    pub id: u64,
+    pub state: DaemonJobState,
+    /// Start of the text or arguments, for listings.
+    pub summary: String,
+    /// Unix time the job was submitted.
+    pub submitted: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// One JSON message per line from daemon to client.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum DaemonResponse {
+    Job { job: DaemonJob },
+    Jobs { jobs: Vec<DaemonJob> },
+    Error { message: String },
+}
+
+pub fn get_socket_path() -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let dir = dirs::runtime_dir()
+        .or_else(dirs::cache_dir)
+        .ok_or("Could not determine runtime directory")?;
+    Ok(dir.join("vp").join("vp.sock"))
+}
+
+fn summarize(job: &JobRequest) -> String {
+    let summary = match &job.stdin {
+        Some(text) => text.split_whitespace().collect::<Vec<_>>().join(" "),
+        None => job
+            .args
+            .iter()
+            .skip(1)
+            .cloned()
+            .collect::<Vec<_>>()
+            .join(" "),
+    };
+    match summary.char_indices().nth(SUMMARY_CHARS) {
+        Some((end, _)) => format!("{}…", &summary[..end]),
+        None => summary,
+    }
+}
+
+#[derive(Default)]
+struct QueueState {
+    next_id: u64,
+    jobs: Vec<DaemonJob>,
+    pending: VecDeque<(u64, JobRequest)>,
+}
+
+/// Jobs shared between connection threads and the thread running them.
+#[derive(Default)]
+struct JobQueue {
+    state: Mutex<QueueState>,
+    /// Signalled when a job is queued or changes state.
+    changed: Condvar,
+}
+
+impl JobQueue {
+    fn submit(&self, request: JobRequest) -> DaemonJob {
+        let mut state = self.state.lock().unwrap();
+        state.next_id += 1;
+        let job = DaemonJob {
+            id: state.next_id,
+            state: DaemonJobState::Queued,
+            summary: summarize(&request),
+            submitted: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default(),
+            error: None,
+        };
+        state.jobs.push(job.clone());
+        state.pending.push_back((job.id, request));
+        self.changed.notify_all();
+        job
+    }
+
+    fn get(&self, id: u64) -> Option<DaemonJob> {
+        let state = self.state.lock().unwrap();
+        state.jobs.iter().find(|job| job.id == id).cloned()
+    }
+
+    fn list(&self) -> Vec<DaemonJob> {
+        self.state.lock().unwrap().jobs.clone()
+    }
+
+    /// Cancels a queued job. A running job cannot be interrupted.
+    fn cancel(&self, id: u64) -> Result<DaemonJob, String> {
+        let mut state = self.state.lock().unwrap();
+        let job = state
+            .jobs
+            .iter_mut()
+            .find(|job| job.id == id)
+            .ok_or_else(|| format!("No job {}", id))?;
+        match job.state {
+            DaemonJobState::Queued => job.state = DaemonJobState::Cancelled,
+            DaemonJobState::Running => {
+                return Err(format!("Job {} is already running", id));
+            }
+            _ => return Err(format!("Job {} has already finished", id)),
+        }
+        let job = job.clone();
+        state.pending.retain(|(pending_id, _)| *pending_id != id);
+        self.changed.notify_all();
+        Ok(job)
+    }
+
+    /// Blocks until a job is queued and marks it as running.
+    fn next(&self) -> (u64, JobRequest) {
+        let mut state = self.state.lock().unwrap();
+        loop {
+            if let Some((id, request)) = state.pending.pop_front() {
+                Self::update(&mut state, id, DaemonJobState::Running, None);
+                self.changed.notify_all();
+                return (id, request);
+            }
+            state = self.changed.wait(state).unwrap();
+        }
+    }
+
+    fn finish(&self, id: u64, result: Result<(), String>) {
+        let mut state = self.state.lock().unwrap();
+        match result {
+            Ok(()) => Self::update(&mut state, id, DaemonJobState::Done, None),
+            Err(e) => Self::update(&mut state, id, DaemonJobState::Failed, Some(e)),
+        }
+
+        let finished = state.jobs.iter().filter(|j| j.state.is_finished()).count();
+        let mut excess = finished.saturating_sub(FINISHED_JOB_HISTORY);
+        state.jobs.retain(|job| {
+            let drop = excess > 0 && job.state.is_finished();
+            if drop {
+                excess -= 1;
+            }
+            !drop
+        });
+        self.changed.notify_all();
+    }
+
+    fn update(state: &mut QueueState, id: u64, new_state: DaemonJobState, error: Option<String>) {
+        if let Some(job) = state.jobs.iter_mut().find(|job| job.id == id) {
+            job.state = new_state;
+            job.error = error;
+        }
+    }
+
+    /// Blocks until the job has finished. Returns `None` if it was dropped
+    /// from the history meanwhile.
+    fn wait(&self, id: u64) -> Option<DaemonJob> {
+        let mut state = self.state.lock().unwrap();
+        loop {
+            let job = state.jobs.iter().find(|job| job.id == id)?;
+            if job.state.is_finished() {
+                return Some(job.clone());
+            }
+            state = self.changed.wait(state).unwrap();
+        }
+    }
+}
+
+fn send(stream: &mut UnixStream, response: &DaemonResponse) -> std::io::Result<()> {
+    let mut line = serde_json::to_vec(response)?;
+    line.push(b'\n');
+    stream.write_all(&line)
+}
+
+fn handle_connection(queue: &JobQueue, stream: UnixStream) -> std::io::Result<()> {
+    let mut writer = stream.try_clone()?;
+    for line in BufReader::new(stream).lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let request = match serde_json::from_str::<DaemonRequest>(&line) {
+            Ok(request) => request,
+            Err(e) => {
+                let message = format!("Invalid request: {}", e);
+                send(&mut writer, &DaemonResponse::Error { message })?;
+                continue;
+            }
+        };
+
+        match request {
+            DaemonRequest::Submit { job, wait } => {
+                let job = queue.submit(job);
+                let id = job.id;
+                send(&mut writer, &DaemonResponse::Job { job })?;
+                if wait {
+                    let response = match queue.wait(id) {
+                        Some(job) => DaemonResponse::Job { job },
+                        None => DaemonResponse::Error {
+                            message: format!("Job {} is no longer known", id),
+                        },
+                    };
+                    send(&mut writer, &response)?;
+                }
+            }
+            DaemonRequest::Status { id } => {
+                let response = match queue.get(id) {
+                    Some(job) => DaemonResponse::Job { job },
+                    None => DaemonResponse::Error {
+                        message: format!("No job {}", id),
+                    },
+                };
+                send(&mut writer, &response)?;
+            }
+            DaemonRequest::Cancel { id } => {
+                let response = match queue.cancel(id) {
+                    Ok(job) => DaemonResponse::Job { job },
+                    Err(message) => DaemonResponse::Error { message },
+                };
+                send(&mut writer, &response)?;
+            }
+            DaemonRequest::List => {
+                let jobs = queue.list();
+                send(&mut writer, &DaemonResponse::Jobs { jobs })?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Listens on `socket_path` and runs submitted jobs one at a time on the
+/// calling thread until the process is stopped.
+pub fn run_daemon<F>(socket_path: &Path, mut run_job: F) -> Result<(), Box<dyn std::error::Error>>
+where
+    F: FnMut(&JobRequest) -> Result<(), Box<dyn std::error::Error>>,
+{
+    if socket_path.exists() {
+        if UnixStream::connect(socket_path).is_ok() {
+            return Err(format!("A daemon is already running on {}", socket_path.display()).into());
+        }
+        // Left behind by a daemon that did not shut down cleanly
+        std::fs::remove_file(socket_path)?;
+    }
+    if let Some(parent) = socket_path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let listener = UnixListener::bind(socket_path)
+        .map_err(|e| format!("Failed to listen on {}: {}", socket_path.display(), e))?;
+    println!("Listening on {}", socket_path.display());
+
+    let queue = Arc::new(JobQueue::default());
+    let listener_queue = Arc::clone(&queue);
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let Ok(stream) = stream else { continue };
+            let queue = Arc::clone(&listener_queue);
+            thread::spawn(move || {
+                if let Err(e) = handle_connection(&queue, stream) {
+                    eprintln!("Connection error: {}", e);
+                }
+            });
+        }
+    });
+
+    loop {
+        let (id, request) = queue.next();
+        println!("Job {}: started", id);
+        let result = run_job(&request).map_err(|e| e.to_string());
+        match &result {
+            Ok(()) => println!("Job {}: done", id),
+            Err(e) => eprintln!("Job {}: failed: {}", id, e),
+        }
+        queue.finish(id, result);
+    }
+}
+
+/// A connection to a running daemon.
+pub struct DaemonClient {
+    writer: UnixStream,
+    reader: BufReader<UnixStream>,
+}
+
+impl DaemonClient {
+    /// Connects to the daemon, failing if none is running.
+    pub fn connect(socket_path: &Path) -> std::io::Result<Self> {
+        let writer = UnixStream::connect(socket_path)?;
+        let reader = BufReader::new(writer.try_clone()?);
+        Ok(Self { writer, reader })
+    }
+
+    pub fn send(&mut self, request: &DaemonRequest) -> Result<(), Box<dyn std::error::Error>> {
+        let mut line = serde_json::to_vec(request)?;
+        line.push(b'\n');
+        self.writer.write_all(&line)?;
+        Ok(())
+    }
+
+    /// Reads the next response. `Error` responses are returned as errors.
+    pub fn receive(&mut self) -> Result<DaemonResponse, Box<dyn std::error::Error>> {
+        let mut line = String::new();
+        if self.reader.read_line(&mut line)? == 0 {
+            return Err("The daemon closed the connection".into());
+        }
+        match serde_json::from_str(&line)? {
+            DaemonResponse::Error { message } => Err(message.into()),
+            response => Ok(response),
+        }
+    }
+
+    pub fn request(
+        &mut self,
+        request: &DaemonRequest,
+    ) -> Result<DaemonResponse, Box<dyn std::error::Error>> {
+        self.send(request)?;
+        self.receive()
+    }
+}
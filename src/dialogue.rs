@@ -0,0 +1,163 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use crate::config::VoicePreset;
+use crate::segment::Segment;
+use crate::voicepeak::VoiceSettings;
+
+/// Parses a dialogue script into one segment per speaker turn.
+///
+/// Each turn starts with `SPEAKER: text` (a full-width `：` also works). The
+/// speaker label is looked up in `speakers` first and then used as a preset
+/// name. Lines without a known label continue the previous turn, so prose
+/// such as `10:30` or `Note: ...` is read as text; lines before the first
+/// label are read with `base_voice`. Blank lines and lines starting with
+/// `#` are ignored.
+pub fn parse_dialogue(
+    text: &str,
+    base_voice: &VoiceSettings,
+    presets: &HashMap<String, VoicePreset>,
+    speakers: &HashMap<String, String>,
+    turn_gap: Duration,
+) -> Result<Vec<Segment>, Box<dyn std::error::Error>> {
+    let mut turns: Vec<(VoiceSettings, String)> = Vec::new();
+
+    for (line_number, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let speaker = split_speaker(line).and_then(|(label, content)| {
+            let preset_name = speakers.get(label).map(|s| s.as_str()).unwrap_or(label);
+            match presets.get(preset_name) {
+                Some(preset) => Some((preset, content)),
+                None => {
+                    if looks_like_label(label, content) {
+                        eprintln!(
+                            "Warning: unknown speaker '{}' on line {} is read as text (add it to [speakers] in config.toml or use a preset name)",
+                            label,
+                            line_number + 1
+                        );
+                    }
+                    None
+                }
+            }
+        });
+
+        match speaker {
+            Some((preset, content)) => {
+                turns.push((preset.voice_settings(), content.to_string()));
+            }
+            None => match turns.last_mut() {
+                Some((_, turn_text)) => {
+                    turn_text.push('\n');
+                    turn_text.push_str(line);
+                }
+                None => {
+                    if base_voice.narrator.is_empty() {
+                        return Err(format!(
+                            "Line {} has no speaker label and no default voice is configured",
+                            line_number + 1
+                        )
+                        .into());
+                    }
+                    turns.push((base_voice.clone(), line.to_string()));
+                }
+            },
+        }
+    }
+
+    Ok(turns
+        .into_iter()
+        .filter(|(_, turn_text)| !turn_text.trim().is_empty())
+        .enumerate()
+        .map(|(i, (voice, turn_text))| Segment {
+            text: turn_text,
+            voice,
+            pause_before: (i > 0).then_some(turn_gap),
+        })
+        .collect())
+}
+
+fn split_speaker(line: &str) -> Option<(&str, &str)> {
+    let (index, separator) = line.char_indices().find(|(_, c)| *c == ':' || *c == '：')?;
+    let label = line[..index].trim();
+    if label.is_empty() {
+        return None;
+    }
+    Some((label, line[index + separator.len_utf8()..].trim()))
+}
+
+/// Whether an unknown label was probably meant as a speaker name rather than
+/// being part of the text (a time, a URL or a phrase).
+fn looks_like_label(label: &str, content: &str) -> bool {
+    label.chars().count() <= 16
+        && !label.chars().any(|c| c.is_whitespace() || c.is_numeric())
+        && !content.starts_with("//")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GAP: Duration = Duration::from_millis(300);
+
+    fn presets() -> HashMap<String, VoicePreset> {
+        ["karin", "tsuyoshi"]
+            .into_iter()
+            .map(|name| {
+                let voice = VoiceSettings {
+                    narrator: format!("{}-narrator", name),
+                    ..VoiceSettings::default()
+                };
+                let preset = VoicePreset::from_voice_settings(name, &voice).unwrap();
+                (name.to_string(), preset)
+            })
+            .collect()
+    }
+
+    fn parse(text: &str, base_narrator: &str) -> Result<Vec<Segment>, Box<dyn std::error::Error>> {
+        let base_voice = VoiceSettings {
+            narrator: base_narrator.to_string(),
+            ..VoiceSettings::default()
+        };
+        let speakers = HashMap::from([("A".to_string(), "karin".to_string())]);
+        parse_dialogue(text, &base_voice, &presets(), &speakers, GAP)
+    }
+
+    #[test]
+    fn splits_turns_by_speaker() {
+        let segments = parse("A: こんにちは\n# comment\n\ntsuyoshi：やあ", "").unwrap();
+        assert_eq!(segments.len(), 2);
+        assert_eq!(segments[0].text, "こんにちは");
+        assert_eq!(segments[0].voice.narrator, "karin-narrator");
+        assert_eq!(segments[0].pause_before, None);
+        assert_eq!(segments[1].text, "やあ");
+        assert_eq!(segments[1].voice.narrator, "tsuyoshi-narrator");
+        assert_eq!(segments[1].pause_before, Some(GAP));
+    }
+
+    #[test]
+    fn unknown_labels_continue_the_turn() {
+        let segments = parse("karin: 集合は\n10:30 です\nNote: 遅れないで", "").unwrap();
+        assert_eq!(segments.len(), 1);
+        assert_eq!(segments[0].text, "集合は\n10:30 です\nNote: 遅れないで");
+    }
+
+    #[test]
+    fn leading_lines_use_the_base_voice() {
+        let segments = parse("前置き\nkarin: 本文", "base").unwrap();
+        assert_eq!(segments[0].voice.narrator, "base");
+        assert_eq!(segments[1].voice.narrator, "karin-narrator");
+
+        assert!(parse("前置き\nkarin: 本文", "").is_err());
+    }
+
+    #[test]
+    fn drops_empty_turns() {
+        let segments = parse("karin:\ntsuyoshi: はい", "").unwrap();
+        assert_eq!(segments.len(), 1);
+        assert_eq!(segments[0].pause_before, None);
+    }
+}
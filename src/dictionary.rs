@@ -0,0 +1,283 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::config::get_config_path;
+
+pub const PROJECT_DICTIONARY_FILE_NAME: &str = ".vp-dict.toml";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DictionaryEntry {
+    pub pattern: String,
+    pub replacement: String,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub regex: bool,
+    /// Only apply when reading with this narrator.
+    pub narrator: Option<String>,
+}
+
+impl DictionaryEntry {
+    fn applies_to(&self, narrator: &str) -> bool {
+        self.narrator.as_deref().is_none_or(|n| n == narrator)
+    }
+
+    /// Checks the pattern, returning the compiled regex for regex entries.
+    fn compile(&self) -> Result<Option<Regex>, Box<dyn std::error::Error>> {
+        // An empty literal would be inserted between every character
+        if self.pattern.is_empty() {
+            return Err("Dictionary pattern must not be empty".into());
+        }
+        if !self.regex {
+            return Ok(None);
+        }
+        let re = Regex::new(&self.pattern)
+            .map_err(|e| format!("Invalid regex '{}': {}", self.pattern, e))?;
+        Ok(Some(re))
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Dictionary {
+    #[serde(default)]
+    pub entries: Vec<DictionaryEntry>,
+    /// Compiled regexes by entry index, filled when the dictionary is loaded
+    /// so they are not rebuilt for every segment.
+    #[serde(skip)]
+    compiled: Vec<Option<Regex>>,
+}
+
+impl Dictionary {
+    /// Checks every entry and compiles the regex entries.
+    fn compile(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        self.compiled = self
+            .entries
+            .iter()
+            .map(|entry| entry.compile())
+            .collect::<Result<_, _>>()?;
+        Ok(())
+    }
+
+    /// Applies every entry matching `narrator` in order.
+    pub fn apply(&self, text: &str, narrator: &str) -> Result<String, Box<dyn std::error::Error>> {
+        let mut result = text.to_string();
+        let compiled = if self.is_compiled() {
+            self.compiled.as_slice()
+        } else {
+            &[]
+        };
+
+        for (i, entry) in self.entries.iter().enumerate() {
+            if !entry.applies_to(narrator) {
+                continue;
+            }
+            result = if entry.regex {
+                // Entries changed since loading are compiled on the spot
+                let fresh;
+                let re = match compiled.get(i).and_then(Option::as_ref) {
+                    Some(re) => re,
+                    None => {
+                        fresh = entry.compile()?;
+                        fresh.as_ref().ok_or("Dictionary regex was not compiled")?
+                    }
+                };
+                re.replace_all(&result, entry.replacement.as_str())
+                    .into_owned()
+            } else {
+                result.replace(&entry.pattern, &entry.replacement)
+            };
+        }
+
+        Ok(result)
+    }
+
+    /// Adds an entry, replacing any existing entry with the same pattern and narrator.
+    pub fn add(&mut self, entry: DictionaryEntry) -> Result<(), Box<dyn std::error::Error>> {
+        entry.compile()?;
+        self.compiled.clear();
+
+        match self
+            .entries
+            .iter_mut()
+            .find(|e| e.pattern == entry.pattern && e.narrator == entry.narrator)
+        {
+            Some(existing) => *existing = entry,
+            None => self.entries.push(entry),
+        }
+        Ok(())
+    }
+
+    fn is_compiled(&self) -> bool {
+        self.compiled.len() == self.entries.len()
+    }
+
+    fn append(&mut self, other: Dictionary) {
+        if self.is_compiled() && other.is_compiled() {
+            self.compiled.extend(other.compiled);
+        } else {
+            self.compiled.clear();
+        }
+        self.entries.extend(other.entries);
+    }
+
+    /// Removes entries with the given pattern (and narrator, if specified).
+    /// Returns the number of removed entries.
+    pub fn remove(&mut self, pattern: &str, narrator: Option<&str>) -> usize {
+        let before = self.entries.len();
+        self.compiled.clear();
+        self.entries.retain(|e| {
+            e.pattern != pattern || narrator.is_some_and(|n| e.narrator.as_deref() != Some(n))
+        });
+        before - self.entries.len()
+    }
+}
+
+pub fn get_dictionary_path() -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let config_path = get_config_path()?;
+    Ok(config_path.with_file_name("dictionary.toml"))
+}
+
+/// Finds the nearest `.vp-dict.toml` in the current directory or its ancestors.
+pub fn find_project_dictionary() -> Option<PathBuf> {
+    let current_dir = std::env::current_dir().ok()?;
+    current_dir
+        .ancestors()
+        .map(|dir| dir.join(PROJECT_DICTIONARY_FILE_NAME))
+        .find(|path| path.is_file())
+}
+
+pub fn load_dictionary(path: &Path) -> Result<Dictionary, Box<dyn std::error::Error>> {
+    if !path.exists() {
+        return Ok(Dictionary::default());
+    }
+
+    let content = fs::read_to_string(path)?;
+    let mut dictionary: Dictionary =
+        toml::from_str(&content).map_err(|e| format!("{}: {}", path.display(), e))?;
+    dictionary
+        .compile()
+        .map_err(|e| format!("{}: {}", path.display(), e))?;
+    Ok(dictionary)
+}
+
+pub fn save_dictionary(
+    path: &Path,
+    dictionary: &Dictionary,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let content = toml::to_string_pretty(dictionary)?;
+    fs::write(path, content)?;
+    Ok(())
+}
+
+/// Loads the project dictionary (if any) followed by the user dictionary, so
+/// project entries are applied first.
+pub fn load_dictionaries() -> Result<Dictionary, Box<dyn std::error::Error>> {
+    let mut dictionary = Dictionary::default();
+
+    if let Some(project_path) = find_project_dictionary() {
+        dictionary.append(load_dictionary(&project_path)?);
+    }
+    dictionary.append(load_dictionary(&get_dictionary_path()?)?);
+
+    Ok(dictionary)
+}
+
+pub fn list_dictionary(dictionary: &Dictionary, source: &Path) {
+    println!("Dictionary: {}", source.display());
+
+    if dictionary.entries.is_empty() {
+        println!("  (no entries)");
+        return;
+    }
+
+    for entry in &dictionary.entries {
+        let kind = if entry.regex { " (regex)" } else { "" };
+        let scope = entry
+            .narrator
+            .as_ref()
+            .map(|n| format!(" [{}]", n))
+            .unwrap_or_default();
+        println!(
+            "  {} -> {}{}{}",
+            entry.pattern, entry.replacement, kind, scope
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(pattern: &str, replacement: &str, regex: bool) -> DictionaryEntry {
+        DictionaryEntry {
+            pattern: pattern.to_string(),
+            replacement: replacement.to_string(),
+            regex,
+            narrator: None,
+        }
+    }
+
+    #[test]
+    fn applies_entries_in_order() {
+        let mut dictionary = Dictionary::default();
+        dictionary
+            .add(entry("VOICEPEAK", "ボイスピーク", false))
+            .unwrap();
+        dictionary
+            .add(entry(r"v(\d+)\.(\d+)", "バージョン${1}.${2}", true))
+            .unwrap();
+        assert_eq!(
+            dictionary.apply("VOICEPEAK v1.2", "").unwrap(),
+            "ボイスピーク バージョン1.2"
+        );
+    }
+
+    #[test]
+    fn uses_regexes_compiled_on_load() {
+        let mut dictionary: Dictionary = toml::from_str(
+            r#"
+            [[entries]]
+            pattern = "(\\d+)円"
+            replacement = "${1} yen"
+            regex = true
+            "#,
+        )
+        .unwrap();
+        dictionary.compile().unwrap();
+        assert!(dictionary.compiled[0].is_some());
+        assert_eq!(dictionary.apply("100円", "").unwrap(), "100 yen");
+    }
+
+    #[test]
+    fn scopes_entries_to_a_narrator() {
+        let mut dictionary = Dictionary::default();
+        dictionary
+            .add(DictionaryEntry {
+                narrator: Some("夏色花梨".to_string()),
+                ..entry("今日", "きょう", false)
+            })
+            .unwrap();
+        assert_eq!(dictionary.apply("今日", "夏色花梨").unwrap(), "きょう");
+        assert_eq!(dictionary.apply("今日", "other").unwrap(), "今日");
+    }
+
+    #[test]
+    fn rejects_empty_and_invalid_patterns() {
+        let mut dictionary = Dictionary::default();
+        assert!(dictionary.add(entry("", "x", false)).is_err());
+        assert!(dictionary.add(entry("(", "x", true)).is_err());
+
+        let mut loaded: Dictionary =
+            toml::from_str("[[entries]]\npattern = \"\"\nreplacement = \"x\"\n").unwrap();
+        assert!(loaded.compile().is_err());
+    }
+
+    #[test]
+    fn add_replaces_an_entry_with_the_same_pattern() {
+        let mut dictionary = Dictionary::default();
+        dictionary.add(entry("a", "b", false)).unwrap();
+        dictionary.add(entry("a", "c", false)).unwrap();
+        assert_eq!(dictionary.entries.len(), 1);
+        assert_eq!(dictionary.remove("a", None), 1);
+    }
+}
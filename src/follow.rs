@@ -0,0 +1,96 @@
+use regex::Regex;
+use std::collections::VecDeque;
+use std::io::BufRead;
+use std::sync::mpsc;
+use std::thread;
+
+/// Include/exclude patterns for `--follow` input lines.
+#[derive(Debug, Default)]
+pub struct LineFilter {
+    include: Vec<Regex>,
+    exclude: Vec<Regex>,
+}
+
+impl LineFilter {
+    pub fn new(include: &[String], exclude: &[String]) -> Result<Self, Box<dyn std::error::Error>> {
+        let compile = |patterns: &[String]| -> Result<Vec<Regex>, Box<dyn std::error::Error>> {
+            patterns
+                .iter()
+                .map(|p| {
+                    Regex::new(p).map_err(|e| format!("Invalid pattern '{}': {}", p, e).into())
+                })
+                .collect()
+        };
+
+        Ok(Self {
+            include: compile(include)?,
+            exclude: compile(exclude)?,
+        })
+    }
+
+    /// A line is kept if it matches any include pattern (or there are none)
+    /// and no exclude pattern.
+    pub fn matches(&self, line: &str) -> bool {
+        (self.include.is_empty() || self.include.iter().any(|re| re.is_match(line)))
+            && !self.exclude.iter().any(|re| re.is_match(line))
+    }
+}
+
+/// Lines read from a stream on a background thread, so new lines keep
+/// arriving while earlier ones are being spoken.
+pub struct LineQueue {
+    receiver: mpsc::Receiver<String>,
+    pending: VecDeque<String>,
+    max_backlog: usize,
+}
+
+impl LineQueue {
+    /// Starts reading `reader` line by line. Blank lines and lines rejected
+    /// by `filter` are dropped as they arrive.
+    pub fn spawn<R: BufRead + Send + 'static>(
+        reader: R,
+        filter: LineFilter,
+        max_backlog: usize,
+    ) -> Self {
+        let (sender, receiver) = mpsc::channel();
+
+        thread::spawn(move || {
+            for line in reader.lines() {
+                let Ok(line) = line else { break };
+                let line = line.trim();
+                if line.is_empty() || !filter.matches(line) {
+                    continue;
+                }
+                if sender.send(line.to_string()).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Self {
+            receiver,
+            pending: VecDeque::new(),
+            max_backlog,
+        }
+    }
+}
+
+impl Iterator for LineQueue {
+    type Item = (String, usize);
+
+    /// Waits for the next line. When more than `max_backlog` lines are
+    /// waiting, the oldest are dropped so speech keeps up with the input.
+    /// Returns the line and the number of dropped lines, or `None` once the
+    /// input has ended and every line has been taken.
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.pending.is_empty() {
+            self.pending.push_back(self.receiver.recv().ok()?);
+        }
+        self.pending.extend(self.receiver.try_iter());
+
+        let dropped = self.pending.len().saturating_sub(self.max_backlog.max(1));
+        self.pending.drain(..dropped);
+
+        self.pending.pop_front().map(|line| (line, dropped))
+    }
+}
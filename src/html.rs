@@ -0,0 +1,319 @@
+use std::path::Path;
+use std::time::Duration;
+
+use crate::segment::Segment;
+use crate::text_util::{as_sentence, decode_entities, find_tag_end, join_lines};
+use crate::voicepeak::VoiceSettings;
+
+const HEADING_PAUSE: Duration = Duration::from_millis(1500);
+const PARAGRAPH_PAUSE: Duration = Duration::from_millis(1000);
+
+/// Elements whose content is never read.
+const SKIPPED_ELEMENTS: &[&str] = &[
+    "head", "script", "style", "noscript", "template", "svg", "nav", "footer", "aside", "form",
+    "button", "select", "iframe", "object", "canvas", "figure",
+];
+
+/// Elements containing raw text that may include unescaped `<`.
+const RAW_TEXT_ELEMENTS: &[&str] = &["script", "style", "textarea"];
+
+const VOID_ELEMENTS: &[&str] = &[
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "source", "track",
+    "wbr",
+];
+
+const PARAGRAPH_ELEMENTS: &[&str] = &[
+    "p",
+    "div",
+    "section",
+    "article",
+    "main",
+    "blockquote",
+    "pre",
+    "ul",
+    "ol",
+    "dl",
+    "table",
+    "hr",
+    "figcaption",
+    "address",
+    "header",
+];
+
+const SENTENCE_ELEMENTS: &[&str] = &["br", "li", "tr", "dt", "dd", "caption"];
+
+/// Whether a file should be read as HTML based on its extension.
+pub fn is_html_path(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.to_ascii_lowercase())
+        .is_some_and(|ext| matches!(ext.as_str(), "html" | "htm" | "xhtml"))
+}
+
+/// Extracts the readable text of an HTML page as one segment per heading or
+/// paragraph.
+///
+/// Scripts, styles, navigation, footers, asides and forms are dropped. If the
+/// page has `<main>` or `<article>` elements, only their content is read.
+/// `<br>`, list items and table rows end a sentence.
+pub fn parse_html(text: &str, voice: &VoiceSettings) -> Vec<Segment> {
+    let nodes = tokenize(text);
+    let has_main = nodes
+        .iter()
+        .any(|node| matches!(node, Node::Start(name) if name == "main" || name == "article"));
+
+    let mut extractor = Extractor::default();
+    let mut skip_depth = 0usize;
+    let mut main_depth = 0usize;
+
+    for node in &nodes {
+        match node {
+            Node::Start(name) => {
+                if SKIPPED_ELEMENTS.contains(&name.as_str()) {
+                    skip_depth += 1;
+                    continue;
+                }
+                if name == "main" || name == "article" {
+                    main_depth += 1;
+                }
+                if skip_depth == 0 {
+                    extractor.start(name);
+                }
+            }
+            Node::End(name) => {
+                if SKIPPED_ELEMENTS.contains(&name.as_str()) {
+                    skip_depth = skip_depth.saturating_sub(1);
+                    continue;
+                }
+                if skip_depth == 0 {
+                    extractor.end(name);
+                }
+                if name == "main" || name == "article" {
+                    main_depth = main_depth.saturating_sub(1);
+                }
+            }
+            Node::Text(content) => {
+                if skip_depth == 0 && (!has_main || main_depth > 0) {
+                    extractor.current.push_str(content);
+                }
+            }
+        }
+    }
+
+    extractor.end_block();
+
+    extractor
+        .blocks
+        .into_iter()
+        .enumerate()
+        .map(|(i, (heading, text))| Segment {
+            text,
+            voice: voice.clone(),
+            pause_before: match (i, heading) {
+                (0, _) => None,
+                (_, true) => Some(HEADING_PAUSE),
+                (_, false) => Some(PARAGRAPH_PAUSE),
+            },
+        })
+        .collect()
+}
+
+#[derive(Default)]
+struct Extractor {
+    /// Finished blocks, flagged `true` for headings.
+    blocks: Vec<(bool, String)>,
+    sentences: Vec<String>,
+    current: String,
+    in_heading: bool,
+}
+
+impl Extractor {
+    fn start(&mut self, name: &str) {
+        if is_heading(name) {
+            self.end_block();
+            self.in_heading = true;
+        } else if PARAGRAPH_ELEMENTS.contains(&name) {
+            self.end_block();
+        } else if SENTENCE_ELEMENTS.contains(&name) {
+            self.end_sentence();
+        } else if matches!(name, "td" | "th") && !self.current.trim().is_empty() {
+            self.current.push('、');
+        }
+    }
+
+    fn end(&mut self, name: &str) {
+        if is_heading(name) || PARAGRAPH_ELEMENTS.contains(&name) {
+            self.end_block();
+        } else if SENTENCE_ELEMENTS.contains(&name) {
+            self.end_sentence();
+        }
+    }
+
+    fn end_sentence(&mut self) {
+        // HTML collapses whitespace; words are rejoined without spaces
+        // between Japanese characters
+        let words: Vec<String> = self
+            .current
+            .split_whitespace()
+            .map(str::to_string)
+            .collect();
+        let sentence = as_sentence(&join_lines(&words));
+        if !sentence.is_empty() {
+            self.sentences.push(sentence);
+        }
+        self.current.clear();
+    }
+
+    fn end_block(&mut self) {
+        self.end_sentence();
+        if !self.sentences.is_empty() {
+            let text = join_lines(&std::mem::take(&mut self.sentences));
+            self.blocks.push((self.in_heading, text));
+        }
+        self.in_heading = false;
+    }
+}
+
+fn is_heading(name: &str) -> bool {
+    matches!(name, "h1" | "h2" | "h3" | "h4" | "h5" | "h6")
+}
+
+enum Node {
+    Text(String),
+    Start(String),
+    End(String),
+}
+
+// Unlike the SSML tokenizer this never fails: malformed markup is read as text
+// or skipped, as browsers do.
+fn tokenize(input: &str) -> Vec<Node> {
+    let mut nodes = Vec::new();
+    let mut rest = input;
+
+    while !rest.is_empty() {
+        let Some(tag_start) = rest.find('<') else {
+            nodes.push(Node::Text(decode_entities(rest)));
+            break;
+        };
+        if tag_start > 0 {
+            nodes.push(Node::Text(decode_entities(&rest[..tag_start])));
+        }
+        rest = &rest[tag_start..];
+
+        if let Some(after) = rest.strip_prefix("<!--") {
+            rest = after.find("-->").map_or("", |end| &after[end + 3..]);
+            continue;
+        }
+        if rest.starts_with("<!") || rest.starts_with("<?") {
+            rest = rest.find('>').map_or("", |end| &rest[end + 1..]);
+            continue;
+        }
+
+        let closing = rest.starts_with("</");
+        let name_start = if closing { 2 } else { 1 };
+        let name: String = rest[name_start..]
+            .chars()
+            .take_while(|c| c.is_ascii_alphanumeric())
+            .collect::<String>()
+            .to_ascii_lowercase();
+        if name.is_empty() {
+            // A stray '<' in text
+            nodes.push(Node::Text("<".to_string()));
+            rest = &rest[1..];
+            continue;
+        }
+
+        let Some(end) = find_tag_end(rest) else {
+            break;
+        };
+        let self_closing = rest[..end].ends_with('/');
+        rest = &rest[end + 1..];
+
+        if closing {
+            nodes.push(Node::End(name));
+            continue;
+        }
+
+        nodes.push(Node::Start(name.clone()));
+        if VOID_ELEMENTS.contains(&name.as_str()) || self_closing {
+            if !VOID_ELEMENTS.contains(&name.as_str()) {
+                nodes.push(Node::End(name));
+            }
+            continue;
+        }
+
+        if RAW_TEXT_ELEMENTS.contains(&name.as_str()) {
+            let close = format!("</{}", name);
+            let content_end = rest.to_ascii_lowercase().find(&close).unwrap_or(rest.len());
+            if name == "textarea" {
+                nodes.push(Node::Text(decode_entities(&rest[..content_end])));
+            }
+            rest = &rest[content_end..];
+        }
+    }
+
+    nodes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(text: &str) -> Vec<Segment> {
+        parse_html(text, &VoiceSettings::default())
+    }
+
+    fn texts(segments: &[Segment]) -> Vec<&str> {
+        segments.iter().map(|s| s.text.as_str()).collect()
+    }
+
+    #[test]
+    fn detects_html_paths() {
+        assert!(is_html_path(Path::new("page.HTM")));
+        assert!(is_html_path(Path::new("book.xhtml")));
+        assert!(!is_html_path(Path::new("page.md")));
+    }
+
+    #[test]
+    fn skips_non_content_elements() {
+        let segments = parse(
+            "<html><head><title>題名</title><style>p > a {}</style></head><body>\
+             <nav>メニュー</nav><p>本文</p><script>if (a < b) {}</script>\
+             <form><button>送信</button></form><footer>フッター</footer></body></html>",
+        );
+        assert_eq!(texts(&segments), ["本文。"]);
+    }
+
+    #[test]
+    fn reads_only_main_content_when_present() {
+        let segments = parse(
+            "<header>サイト名</header><main><h1>記事</h1><p>内容です。</p>\
+             <h2>次</h2></main><p>関連記事</p>",
+        );
+        assert_eq!(texts(&segments), ["記事。", "内容です。", "次。"]);
+        assert_eq!(segments[0].pause_before, None);
+        assert_eq!(segments[1].pause_before, Some(PARAGRAPH_PAUSE));
+        assert_eq!(segments[2].pause_before, Some(HEADING_PAUSE));
+
+        let segments = parse("<header>サイト名</header><p>本文</p>");
+        assert_eq!(texts(&segments), ["サイト名。", "本文。"]);
+    }
+
+    #[test]
+    fn breaks_lists_and_tables_end_sentences() {
+        let segments = parse(
+            "<p>一行目<br>二行目</p><ul><li>A</li><li>B</li></ul>\
+             <table><tr><th>名前</th><th>値</th></tr><tr><td>a</td><td>1</td></tr></table>",
+        );
+        assert_eq!(
+            texts(&segments),
+            ["一行目。二行目。", "A. B.", "名前、値。a、1."]
+        );
+    }
+
+    #[test]
+    fn tolerates_malformed_markup() {
+        let segments = parse("<!-- note --><!DOCTYPE html><p>a < b &amp;\n c</p><p>unclosed");
+        assert_eq!(texts(&segments), ["a < b & c.", "unclosed."]);
+    }
+}
@@ -0,0 +1,237 @@
+use fs2::FileExt;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::manifest::chunk_file_name;
+use crate::metadata::AudioMetadata;
+use crate::segment::Segment;
+use crate::subtitles::SubtitleUnit;
+
+pub const JOB_STATE_FILE_NAME: &str = "job.json";
+
+/// Everything needed to finish a file render, saved as `job.json` so an
+/// interrupted render can be resumed by another process.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JobState {
+    pub id: String,
+    /// Seconds since the Unix epoch.
+    pub created: u64,
+    pub chunks: Vec<Segment>,
+    pub output: Option<PathBuf>,
+    pub split_output: Option<PathBuf>,
+    pub subtitles: Option<PathBuf>,
+    pub subtitle_unit: SubtitleUnit,
+    pub metadata: Option<AudioMetadata>,
+}
+
+/// Length of the hex job IDs made by `plan_id`.
+const JOB_ID_LEN: usize = 12;
+
+/// A render whose completed chunks are kept in a job directory until the
+/// final output has been written.
+pub struct Job {
+    dir: PathBuf,
+    pub state: JobState,
+    /// Held while the job is rendered or removed, so two processes never
+    /// work on the same directory. Listed jobs are not locked.
+    lock: Option<File>,
+}
+
+pub fn get_jobs_dir() -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let data_dir = dirs::data_local_dir().ok_or("Could not determine data directory")?;
+    Ok(data_dir.join("vp").join("jobs"))
+}
+
+// Output paths are stored absolute so `vp resume` works from any directory
+fn absolute(path: &Path) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    if path.is_absolute() {
+        Ok(path.to_path_buf())
+    } else {
+        Ok(std::env::current_dir()?.join(path))
+    }
+}
+
+fn validate_id(id: &str) -> Result<(), Box<dyn std::error::Error>> {
+    if id.len() != JOB_ID_LEN || !id.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f')) {
+        return Err(format!("Invalid job ID: {}", id).into());
+    }
+    Ok(())
+}
+
+/// Takes the lock of job `id`, failing if another process holds it.
+fn lock_job(id: &str) -> Result<File, Box<dyn std::error::Error>> {
+    let jobs_dir = get_jobs_dir()?;
+    fs::create_dir_all(&jobs_dir)?;
+    let lock = fs::OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(jobs_dir.join(format!("{}.lock", id)))?;
+    lock.try_lock_exclusive()
+        .map_err(|_| format!("Job {} is being rendered by another process", id))?;
+    Ok(lock)
+}
+
+impl Job {
+    /// Creates the job directory for a render. The job ID is derived from the
+    /// chunks and outputs, so the same command maps to the same job: with
+    /// `resume` its rendered chunks are kept, otherwise it starts over.
+    pub fn prepare(
+        chunks: &[Segment],
+        output: Option<&Path>,
+        split_output: Option<&Path>,
+        subtitles: Option<(&Path, SubtitleUnit)>,
+        metadata: Option<&AudioMetadata>,
+        resume: bool,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut state = JobState {
+            id: String::new(),
+            created: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default(),
+            chunks: chunks.to_vec(),
+            output: output.map(absolute).transpose()?,
+            split_output: split_output.map(absolute).transpose()?,
+            subtitles: subtitles.map(|(path, _)| absolute(path)).transpose()?,
+            subtitle_unit: subtitles.map_or(SubtitleUnit::Chunk, |(_, unit)| unit),
+            metadata: metadata.cloned(),
+        };
+        state.id = plan_id(&state)?;
+
+        let lock = lock_job(&state.id)?;
+        let dir = get_jobs_dir()?.join(&state.id);
+        if dir.exists() && resume {
+            return Self::load(&state.id, Some(lock));
+        }
+        if dir.exists() {
+            fs::remove_dir_all(&dir)?;
+        }
+        fs::create_dir_all(&dir)?;
+        fs::write(
+            dir.join(JOB_STATE_FILE_NAME),
+            serde_json::to_string_pretty(&state)?,
+        )?;
+
+        Ok(Self {
+            dir,
+            state,
+            lock: Some(lock),
+        })
+    }
+
+    /// Opens job `id` for rendering or removal.
+    pub fn open(id: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        validate_id(id)?;
+        let lock = lock_job(id)?;
+        Self::load(id, Some(lock))
+    }
+
+    fn load(id: &str, lock: Option<File>) -> Result<Self, Box<dyn std::error::Error>> {
+        validate_id(id)?;
+        let dir = get_jobs_dir()?.join(id);
+        let state_path = dir.join(JOB_STATE_FILE_NAME);
+        if !state_path.is_file() {
+            return Err(format!("No job found: {}", id).into());
+        }
+
+        let state: JobState = serde_json::from_str(&fs::read_to_string(&state_path)?)
+            .map_err(|e| format!("{}: {}", state_path.display(), e))?;
+        Ok(Self { dir, state, lock })
+    }
+
+    /// Deletes job `id` without finishing it. Works on jobs whose state
+    /// file cannot be read.
+    pub fn discard(id: &str) -> Result<(), Box<dyn std::error::Error>> {
+        validate_id(id)?;
+        let _lock = lock_job(id)?;
+        let dir = get_jobs_dir()?.join(id);
+        if !dir.is_dir() {
+            return Err(format!("No job found: {}", id).into());
+        }
+        fs::remove_dir_all(&dir)?;
+        let _ = fs::remove_file(dir.with_extension("lock"));
+        Ok(())
+    }
+
+    pub fn id(&self) -> &str {
+        &self.state.id
+    }
+
+    pub fn chunk_path(&self, index: usize) -> PathBuf {
+        self.dir.join(chunk_file_name(index))
+    }
+
+    /// Where a chunk is synthesized before being moved to `chunk_path`, so a
+    /// killed render never leaves a truncated chunk behind.
+    pub fn partial_chunk_path(&self, index: usize) -> PathBuf {
+        self.dir.join(format!("partial-{}", chunk_file_name(index)))
+    }
+
+    pub fn chunk_files(&self) -> Vec<PathBuf> {
+        (0..self.state.chunks.len())
+            .map(|i| self.chunk_path(i))
+            .collect()
+    }
+
+    pub fn rendered_count(&self) -> usize {
+        self.chunk_files().iter().filter(|p| p.is_file()).count()
+    }
+
+    /// Whether another process is rendering this listed job.
+    pub fn is_running(&self) -> bool {
+        self.lock.is_none() && lock_job(self.id()).is_err()
+    }
+
+    /// Deletes the job directory, once the output has been written or when
+    /// the job is discarded.
+    pub fn remove(self) -> Result<(), Box<dyn std::error::Error>> {
+        if self.lock.is_none() {
+            return Err(format!("Job {} is not open", self.id()).into());
+        }
+        fs::remove_dir_all(&self.dir)?;
+        let _ = fs::remove_file(self.dir.with_extension("lock"));
+        Ok(())
+    }
+}
+
+fn plan_id(state: &JobState) -> Result<String, Box<dyn std::error::Error>> {
+    let plan = serde_json::to_vec(&(
+        &state.chunks,
+        &state.output,
+        &state.split_output,
+        &state.subtitles,
+        &state.subtitle_unit,
+        &state.metadata,
+    ))?;
+    let digest = Sha256::digest(&plan);
+    Ok(digest[..JOB_ID_LEN / 2]
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect())
+}
+
+/// Lists unfinished jobs, oldest first.
+pub fn list_jobs() -> Result<Vec<Job>, Box<dyn std::error::Error>> {
+    let jobs_dir = get_jobs_dir()?;
+    if !jobs_dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut jobs = Vec::new();
+    for entry in fs::read_dir(&jobs_dir)? {
+        let entry = entry?;
+        if let Some(id) = entry.file_name().to_str() {
+            // Skip lock files and directories without a readable state file
+            if let Ok(job) = Job::load(id, None) {
+                jobs.push(job);
+            }
+        }
+    }
+    jobs.sort_by_key(|job| job.state.created);
+
+    Ok(jobs)
+}
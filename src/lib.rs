@@ -1,17 +1,64 @@
 pub mod audio;
 pub mod audio_merge;
+pub mod batch;
+pub mod cache;
 pub mod cli;
 pub mod config;
+#[cfg(unix)]
+pub mod daemon;
+pub mod dialogue;
+pub mod dictionary;
 pub mod env_check;
+pub mod follow;
+pub mod html;
+pub mod job;
+pub mod manifest;
+pub mod markdown;
+pub mod markup;
+pub mod metadata;
+pub mod normalize;
+pub mod openai;
 pub mod presets;
+pub mod render;
+pub mod repl;
+pub mod segment;
+pub mod server;
+pub mod ssml;
+pub mod subtitles;
 pub mod text_splitter;
+mod text_util;
 pub mod voicepeak;
+pub mod voicevox;
+pub mod watch;
 
 pub use audio::*;
 pub use audio_merge::*;
+pub use batch::*;
+pub use cache::*;
 pub use cli::*;
 pub use config::*;
+#[cfg(unix)]
+pub use daemon::*;
+pub use dialogue::*;
+pub use dictionary::*;
 pub use env_check::*;
+pub use follow::*;
+pub use html::*;
+pub use job::*;
+pub use manifest::*;
+pub use markdown::*;
+pub use markup::*;
+pub use metadata::*;
+pub use normalize::*;
+pub use openai::*;
 pub use presets::VoicePreset;
+pub use render::*;
+pub use repl::*;
+pub use segment::*;
+pub use server::*;
+pub use ssml::*;
+pub use subtitles::*;
 pub use text_splitter::*;
 pub use voicepeak::*;
+pub use voicevox::*;
+pub use watch::*;
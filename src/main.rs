@@ -1,13 +1,24 @@
-use voicepeak_cli::cli::{build_cli, handle_matches};
+use voicepeak_cli::cli::{build_cli, handle_matches, requires_environment, submit_to_daemon};
 use voicepeak_cli::env_check::check_environment;
 
 fn main() {
-    if let Err(e) = check_environment() {
-        eprintln!("Environment check failed: {}", e);
-        std::process::exit(1);
+    let matches = build_cli().get_matches();
+
+    // A running daemon has already checked the environment
+    if let Some(result) = submit_to_daemon(&matches) {
+        if let Err(e) = result {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+        return;
     }
 
-    let matches = build_cli().get_matches();
+    if requires_environment(&matches) {
+        if let Err(e) = check_environment() {
+            eprintln!("Environment check failed: {}", e);
+            std::process::exit(1);
+        }
+    }
 
     if let Err(e) = handle_matches(matches) {
         eprintln!("Error: {}", e);
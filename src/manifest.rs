@@ -0,0 +1,72 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::audio::wav_duration;
+use crate::metadata::{write_metadata, AudioMetadata};
+use crate::segment::Segment;
+
+pub const MANIFEST_FILE_NAME: &str = "manifest.json";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    pub file: String,
+    pub text: String,
+    pub narrator: String,
+    pub emotion: String,
+    pub pitch: Option<i32>,
+    pub speed: Option<i32>,
+    /// Duration of the clip in seconds.
+    pub duration: f64,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Manifest {
+    pub chunks: Vec<ManifestEntry>,
+}
+
+pub fn chunk_file_name(index: usize) -> String {
+    format!("{:04}.wav", index + 1)
+}
+
+/// Copies generated chunk files into `dir` as `0001.wav`, `0002.wav`, ... and
+/// writes a `manifest.json` describing each clip. When `metadata` is given,
+/// each clip is tagged with it, using the clip's own text.
+pub fn write_split_output(
+    dir: &Path,
+    chunks: &[Segment],
+    files: &[PathBuf],
+    metadata: Option<&AudioMetadata>,
+) -> Result<Manifest, Box<dyn std::error::Error>> {
+    fs::create_dir_all(dir)?;
+
+    let mut manifest = Manifest::default();
+
+    for (i, (chunk, file)) in chunks.iter().zip(files).enumerate() {
+        let file_name = chunk_file_name(i);
+        let clip_path = dir.join(&file_name);
+        fs::copy(file, &clip_path)?;
+        if let Some(metadata) = metadata {
+            let clip_metadata = AudioMetadata {
+                text: Some(chunk.text.clone()),
+                ..metadata.clone()
+            };
+            write_metadata(&clip_path, &clip_metadata)?;
+        }
+
+        manifest.chunks.push(ManifestEntry {
+            file: file_name,
+            text: chunk.text.clone(),
+            narrator: chunk.voice.narrator.clone(),
+            emotion: chunk.voice.emotion.clone(),
+            pitch: chunk.voice.pitch.as_deref().and_then(|p| p.parse().ok()),
+            speed: chunk.voice.speed.as_deref().and_then(|s| s.parse().ok()),
+            duration: wav_duration(file)?.as_secs_f64(),
+        });
+    }
+
+    let content = serde_json::to_string_pretty(&manifest)?;
+    fs::write(dir.join(MANIFEST_FILE_NAME), content)?;
+
+    Ok(manifest)
+}
@@ -0,0 +1,376 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::time::Duration;
+
+use crate::segment::Segment;
+use crate::text_util::{as_sentence, join_lines};
+use crate::voicepeak::VoiceSettings;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CodeBlockMode {
+    Skip,
+    /// Replace each code block with `code_announcement`.
+    Announce,
+    Read,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MarkdownConfig {
+    pub code_blocks: CodeBlockMode,
+    pub code_announcement: String,
+    /// Silence before a heading, in milliseconds.
+    pub heading_pause_ms: u64,
+    /// Silence before a paragraph, list or table, in milliseconds.
+    pub paragraph_pause_ms: u64,
+}
+
+impl Default for MarkdownConfig {
+    fn default() -> Self {
+        Self {
+            code_blocks: CodeBlockMode::Skip,
+            code_announcement: "コードは省略します。".to_string(),
+            heading_pause_ms: 2000,
+            paragraph_pause_ms: 1500,
+        }
+    }
+}
+
+/// Whether a file should be read as Markdown based on its extension.
+pub fn is_markdown_path(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.to_ascii_lowercase())
+        .is_some_and(|ext| matches!(ext.as_str(), "md" | "markdown"))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BlockKind {
+    Heading,
+    Paragraph,
+}
+
+/// Strips Markdown syntax and returns one segment per heading or paragraph,
+/// separated by the pauses configured in `config`.
+///
+/// Link and emphasis markers are removed, images, HTML comments and reference
+/// definitions are dropped, list items and table rows become sentences, and
+/// code blocks are skipped, announced or read depending on `code_blocks`.
+pub fn parse_markdown(text: &str, voice: &VoiceSettings, config: &MarkdownConfig) -> Vec<Segment> {
+    let rules = InlineRules::new();
+    let mut blocks: Vec<(BlockKind, String)> = Vec::new();
+    let mut paragraph: Vec<String> = Vec::new();
+    let mut code_fence: Option<(String, Vec<String>)> = None;
+    let mut in_comment = false;
+    let mut lines = text.lines().peekable();
+
+    // YAML front matter
+    if lines.peek().is_some_and(|line| line.trim() == "---") {
+        lines.next();
+        for line in lines.by_ref() {
+            if matches!(line.trim(), "---" | "...") {
+                break;
+            }
+        }
+    }
+
+    for line in lines {
+        let trimmed = line.trim();
+
+        if let Some((fence, code)) = code_fence.as_mut() {
+            if trimmed.starts_with(fence.as_str()) {
+                match config.code_blocks {
+                    CodeBlockMode::Skip => {}
+                    CodeBlockMode::Announce => {
+                        blocks.push((BlockKind::Paragraph, config.code_announcement.clone()))
+                    }
+                    CodeBlockMode::Read => {
+                        let code: Vec<String> = code.iter().map(|l| as_sentence(l)).collect();
+                        blocks.push((BlockKind::Paragraph, join_lines(&code)));
+                    }
+                }
+                code_fence = None;
+            } else {
+                code.push(line.trim().to_string());
+            }
+            continue;
+        }
+
+        if in_comment {
+            in_comment = !trimmed.contains("-->");
+            continue;
+        }
+        if trimmed.starts_with("<!--") {
+            in_comment = !trimmed.contains("-->");
+            continue;
+        }
+
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            flush_paragraph(&mut paragraph, &mut blocks);
+            code_fence = Some((trimmed[..3].to_string(), Vec::new()));
+            continue;
+        }
+
+        if trimmed.is_empty() {
+            flush_paragraph(&mut paragraph, &mut blocks);
+            continue;
+        }
+
+        if let Some(caps) = rules.heading.captures(trimmed) {
+            flush_paragraph(&mut paragraph, &mut blocks);
+            blocks.push((BlockKind::Heading, as_sentence(&rules.strip(&caps[1]))));
+            continue;
+        }
+
+        // Setext headings underline the previous line with === or ---
+        if !paragraph.is_empty() && rules.setext_underline.is_match(trimmed) {
+            let heading = join_lines(&std::mem::take(&mut paragraph));
+            blocks.push((BlockKind::Heading, as_sentence(&heading)));
+            continue;
+        }
+        if rules.thematic_break.is_match(trimmed) {
+            flush_paragraph(&mut paragraph, &mut blocks);
+            continue;
+        }
+        if rules.reference_definition.is_match(trimmed) {
+            continue;
+        }
+
+        if trimmed.starts_with('|') {
+            if !rules.table_separator.is_match(trimmed) {
+                let cells: Vec<String> = trimmed
+                    .trim_matches('|')
+                    .split('|')
+                    .map(|cell| rules.strip(cell.trim()))
+                    .filter(|cell| !cell.is_empty())
+                    .collect();
+                paragraph.push(as_sentence(&cells.join("、")));
+            }
+            continue;
+        }
+
+        let mut content = trimmed;
+        while let Some(rest) = content.strip_prefix('>') {
+            content = rest.trim_start();
+        }
+
+        if let Some(caps) = rules.list_item.captures(content) {
+            paragraph.push(as_sentence(&rules.strip(&caps[1])));
+        } else {
+            paragraph.push(rules.strip(content));
+        }
+    }
+
+    flush_paragraph(&mut paragraph, &mut blocks);
+
+    let heading_pause = Duration::from_millis(config.heading_pause_ms);
+    let paragraph_pause = Duration::from_millis(config.paragraph_pause_ms);
+
+    blocks
+        .into_iter()
+        .filter(|(_, text)| !text.trim().is_empty())
+        .enumerate()
+        .map(|(i, (kind, text))| Segment {
+            text,
+            voice: voice.clone(),
+            pause_before: match (i, kind) {
+                (0, _) => None,
+                (_, BlockKind::Heading) => Some(heading_pause),
+                (_, BlockKind::Paragraph) => Some(paragraph_pause),
+            },
+        })
+        .collect()
+}
+
+fn flush_paragraph(paragraph: &mut Vec<String>, blocks: &mut Vec<(BlockKind, String)>) {
+    if paragraph.is_empty() {
+        return;
+    }
+    blocks.push((BlockKind::Paragraph, join_lines(paragraph)));
+    paragraph.clear();
+}
+
+const ESCAPABLE: &str = "\\`*_{}[]()#+-.!|<>~";
+
+fn placeholder(escaped: char) -> char {
+    let index = ESCAPABLE.chars().position(|c| c == escaped).unwrap_or(0);
+    char::from_u32(0xE000 + index as u32).unwrap_or(escaped)
+}
+
+struct InlineRules {
+    heading: Regex,
+    setext_underline: Regex,
+    thematic_break: Regex,
+    reference_definition: Regex,
+    table_separator: Regex,
+    list_item: Regex,
+    replacements: Vec<(Regex, &'static str)>,
+}
+
+impl InlineRules {
+    fn new() -> Self {
+        // Patterns are compile-time constants, so failing to compile is a bug
+        let re = |pattern: &str| Regex::new(pattern).expect("invalid markdown pattern");
+
+        Self {
+            heading: re(r"^#{1,6}\s+(.*?)(?:\s+#+)?\s*$"),
+            setext_underline: re(r"^(?:=+|-{2,})$"),
+            thematic_break: re(r"^(?:(?:-\s*){3,}|(?:\*\s*){3,}|(?:_\s*){3,})$"),
+            reference_definition: re(r"^\[[^\]]+\]:\s*\S+"),
+            table_separator: re(r"^\|?\s*:?-+:?\s*(?:\|\s*:?-+:?\s*)*\|?$"),
+            list_item: re(r"^(?:[-*+]|\d+[.)])\s+(?:\[[ xX]\]\s+)?(.*)$"),
+            replacements: vec![
+                // Images are dropped, links keep their text
+                (re(r"!\[[^\]]*\](?:\([^)]*\)|\[[^\]]*\])"), ""),
+                (re(r"\[([^\]]*)\](?:\([^)]*\)|\[[^\]]*\])"), "$1"),
+                (re(r"<(https?://[^>]+)>"), "$1"),
+                (re(r"</?[A-Za-z][^>]*>"), ""),
+                (re(r"`+([^`]*)`+"), "$1"),
+                (re(r"\*\*(.+?)\*\*"), "$1"),
+                (re(r"__(.+?)__"), "$1"),
+                (re(r"\*(\S(?:.*?\S)?)\*"), "$1"),
+                (re(r"(^|\W)_(\S(?:.*?\S)?)_(\W|$)"), "$1$2$3"),
+                (re(r"~~(.+?)~~"), "$1"),
+            ],
+        }
+    }
+
+    fn strip(&self, text: &str) -> String {
+        // Escaped characters are swapped for private-use placeholders so the
+        // rules below leave them alone, then restored as literals.
+        let mut result = String::with_capacity(text.len());
+        let mut chars = text.chars();
+        while let Some(ch) = chars.next() {
+            match (ch, chars.clone().next()) {
+                ('\\', Some(next)) if ESCAPABLE.contains(next) => {
+                    chars.next();
+                    result.push(placeholder(next));
+                }
+                _ => result.push(ch),
+            }
+        }
+
+        for (pattern, replacement) in &self.replacements {
+            result = pattern.replace_all(&result, *replacement).into_owned();
+        }
+
+        result
+            .chars()
+            .map(|ch| {
+                ESCAPABLE
+                    .chars()
+                    .find(|&escaped| placeholder(escaped) == ch)
+                    .unwrap_or(ch)
+            })
+            .collect::<String>()
+            .trim()
+            .to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(text: &str, code_blocks: CodeBlockMode) -> Vec<Segment> {
+        let config = MarkdownConfig {
+            code_blocks,
+            ..MarkdownConfig::default()
+        };
+        parse_markdown(text, &VoiceSettings::default(), &config)
+    }
+
+    fn texts(segments: &[Segment]) -> Vec<&str> {
+        segments.iter().map(|s| s.text.as_str()).collect()
+    }
+
+    #[test]
+    fn detects_markdown_paths() {
+        assert!(is_markdown_path(Path::new("notes/README.MD")));
+        assert!(is_markdown_path(Path::new("post.markdown")));
+        assert!(!is_markdown_path(Path::new("notes.txt")));
+    }
+
+    #[test]
+    fn headings_and_paragraphs_get_their_own_pauses() {
+        let segments = parse(
+            "---\ntitle: 無視\n---\n# 見出し #\n\n本文の\n続きです。\n\n## Next",
+            CodeBlockMode::Skip,
+        );
+        assert_eq!(texts(&segments), ["見出し。", "本文の続きです。", "Next."]);
+        assert_eq!(segments[0].pause_before, None);
+        assert_eq!(segments[1].pause_before, Some(Duration::from_millis(1500)));
+        assert_eq!(segments[2].pause_before, Some(Duration::from_millis(2000)));
+    }
+
+    #[test]
+    fn reads_setext_headings() {
+        let segments = parse(
+            "Intro\n\nTitle\n=====\nBody\n\nSub\n---",
+            CodeBlockMode::Skip,
+        );
+        assert_eq!(texts(&segments), ["Intro", "Title.", "Body", "Sub."]);
+        assert_eq!(segments[1].pause_before, Some(Duration::from_millis(2000)));
+        assert_eq!(segments[3].pause_before, Some(Duration::from_millis(2000)));
+    }
+
+    #[test]
+    fn code_fences_are_skipped_announced_or_read() {
+        let text = "前。\n\n```rust\nlet x = 1;\n# not a heading\n```\n\n~~~\nplain\n~~~\n後。";
+        assert_eq!(texts(&parse(text, CodeBlockMode::Skip)), ["前。", "後。"]);
+        assert_eq!(
+            texts(&parse(text, CodeBlockMode::Announce)),
+            [
+                "前。",
+                "コードは省略します。",
+                "コードは省略します。",
+                "後。"
+            ]
+        );
+        assert_eq!(
+            texts(&parse(text, CodeBlockMode::Read)),
+            ["前。", "let x = 1;. # not a heading.", "plain.", "後。"]
+        );
+    }
+
+    #[test]
+    fn lists_and_tables_become_sentences() {
+        let segments = parse(
+            "- りんご\n* [x] done\n1. 三番目\n\n| 名前 | 値 |\n|:---|---:|\n| a | 1 |",
+            CodeBlockMode::Skip,
+        );
+        assert_eq!(
+            texts(&segments),
+            ["りんご。done.三番目。", "名前、値。a、1."]
+        );
+    }
+
+    #[test]
+    fn drops_comments_references_and_breaks() {
+        let segments = parse(
+            "<!-- hidden\nstill hidden -->\n表示\n\n***\n\n[ref]: https://example.com\n> 引用",
+            CodeBlockMode::Skip,
+        );
+        assert_eq!(texts(&segments), ["表示", "引用"]);
+    }
+
+    #[test]
+    fn strips_inline_syntax() {
+        let rules = InlineRules::new();
+        assert_eq!(
+            rules.strip("[リンク](https://example.com)と![画像](a.png)と`code`"),
+            "リンクととcode"
+        );
+        assert_eq!(
+            rules.strip("**太字** *斜体* ~~消し~~ <b>tag</b>"),
+            "太字 斜体 消し tag"
+        );
+        assert_eq!(
+            rules.strip("snake_case_name and _em_"),
+            "snake_case_name and em"
+        );
+        assert_eq!(rules.strip(r"\*not emphasis\* \[x\]"), "*not emphasis* [x]");
+    }
+}
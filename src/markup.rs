@@ -0,0 +1,306 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use crate::config::VoicePreset;
+use crate::segment::Segment;
+use crate::voicepeak::VoiceSettings;
+
+/// Parses inline tags into segments, each carrying its own voice settings.
+///
+/// Supported tags:
+/// - `[narrator=NAME emotion=happy=50 pitch=20 speed=110]` overrides individual parameters
+/// - `[preset NAME]` switches to a preset
+/// - `[pause 800ms]` inserts silence (`ms` or `s`, milliseconds if no unit)
+///   before the text that follows it
+/// - `[reset]` returns to the voice the command started with
+///
+/// A literal bracket can be written as `\[`.
+pub fn parse_markup(
+    text: &str,
+    base_voice: &VoiceSettings,
+    presets: &HashMap<String, VoicePreset>,
+) -> Result<Vec<Segment>, Box<dyn std::error::Error>> {
+    let mut segments = Vec::new();
+    let mut voice = base_voice.clone();
+    let mut pending_pause: Option<Duration> = None;
+    let mut current_text = String::new();
+    let mut chars = text.chars();
+
+    while let Some(ch) = chars.next() {
+        match ch {
+            '\\' => match chars.next() {
+                Some(next @ ('[' | ']' | '\\')) => current_text.push(next),
+                Some(next) => {
+                    current_text.push('\\');
+                    current_text.push(next);
+                }
+                None => current_text.push('\\'),
+            },
+            '[' => {
+                let mut tag = String::new();
+                let mut closed = false;
+                for tag_ch in chars.by_ref() {
+                    if tag_ch == ']' {
+                        closed = true;
+                        break;
+                    }
+                    tag.push(tag_ch);
+                }
+                if !closed {
+                    return Err(format!("Unclosed markup tag: [{}", tag).into());
+                }
+
+                flush_segment(&mut segments, &mut current_text, &voice, &mut pending_pause);
+
+                match apply_tag(&tag, &mut voice, base_voice, presets)? {
+                    TagEffect::Pause(pause) => {
+                        pending_pause = Some(pending_pause.unwrap_or_default() + pause);
+                    }
+                    TagEffect::VoiceChanged => {}
+                }
+            }
+            _ => current_text.push(ch),
+        }
+    }
+
+    flush_segment(&mut segments, &mut current_text, &voice, &mut pending_pause);
+    // Pauses are inserted before the next segment, so a trailing one would be
+    // silently dropped
+    if pending_pause.is_some() {
+        return Err("A [pause] tag must be followed by text".into());
+    }
+
+    Ok(segments)
+}
+
+enum TagEffect {
+    Pause(Duration),
+    VoiceChanged,
+}
+
+fn flush_segment(
+    segments: &mut Vec<Segment>,
+    current_text: &mut String,
+    voice: &VoiceSettings,
+    pending_pause: &mut Option<Duration>,
+) {
+    if current_text.trim().is_empty() {
+        current_text.clear();
+        return;
+    }
+
+    segments.push(Segment {
+        text: current_text.trim().to_string(),
+        voice: voice.clone(),
+        pause_before: pending_pause.take(),
+    });
+    current_text.clear();
+}
+
+fn apply_tag(
+    tag: &str,
+    voice: &mut VoiceSettings,
+    base_voice: &VoiceSettings,
+    presets: &HashMap<String, VoicePreset>,
+) -> Result<TagEffect, Box<dyn std::error::Error>> {
+    let words = split_tag_words(tag)?;
+    let Some(first) = words.first() else {
+        return Err("Empty markup tag: []".into());
+    };
+
+    // Keyword forms: [pause 800ms], [preset NAME], [reset]
+    match (first.as_str(), words.len()) {
+        ("pause", 2) => return Ok(TagEffect::Pause(parse_duration(&words[1])?)),
+        ("preset", 2) => {
+            *voice = lookup_preset(&words[1], presets)?.voice_settings();
+            return Ok(TagEffect::VoiceChanged);
+        }
+        ("reset", 1) => {
+            *voice = base_voice.clone();
+            return Ok(TagEffect::VoiceChanged);
+        }
+        _ => {}
+    }
+
+    for word in &words {
+        let (key, value) = word
+            .split_once('=')
+            .ok_or_else(|| format!("Invalid markup tag: [{}]", tag))?;
+        match key {
+            "pause" => {
+                if words.len() > 1 {
+                    return Err(
+                        format!("pause cannot be combined with other keys: [{}]", tag).into(),
+                    );
+                }
+                return Ok(TagEffect::Pause(parse_duration(value)?));
+            }
+            "preset" => *voice = lookup_preset(value, presets)?.voice_settings(),
+            "narrator" => voice.narrator = value.to_string(),
+            "emotion" => voice.emotion = value.to_string(),
+            "pitch" => voice.pitch = Some(value.to_string()),
+            "speed" => voice.speed = Some(value.to_string()),
+            _ => return Err(format!("Unknown markup key '{}' in [{}]", key, tag).into()),
+        }
+    }
+
+    Ok(TagEffect::VoiceChanged)
+}
+
+fn lookup_preset<'a>(
+    name: &str,
+    presets: &'a HashMap<String, VoicePreset>,
+) -> Result<&'a VoicePreset, Box<dyn std::error::Error>> {
+    presets
+        .get(name)
+        .ok_or_else(|| format!("Unknown preset: {}", name).into())
+}
+
+// Splits on whitespace, keeping double-quoted values (e.g. narrator="Some Name") together
+fn split_tag_words(tag: &str) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let mut words = Vec::new();
+    let mut current = String::new();
+    let mut in_quotes = false;
+
+    for ch in tag.chars() {
+        match ch {
+            '"' => in_quotes = !in_quotes,
+            c if c.is_whitespace() && !in_quotes => {
+                if !current.is_empty() {
+                    words.push(std::mem::take(&mut current));
+                }
+            }
+            c => current.push(c),
+        }
+    }
+
+    if in_quotes {
+        return Err(format!("Unterminated quote in markup tag: [{}]", tag).into());
+    }
+    if !current.is_empty() {
+        words.push(current);
+    }
+
+    Ok(words)
+}
+
+/// Longest pause, gap or interval accepted from markup, SSML or options.
+pub const MAX_DURATION: Duration = Duration::from_secs(60);
+
+/// Parses `800ms`, `1.5s` or a bare number of milliseconds, up to
+/// [`MAX_DURATION`].
+pub fn parse_duration(value: &str) -> Result<Duration, Box<dyn std::error::Error>> {
+    let value = value.trim();
+    let (number, scale) = if let Some(ms) = value.strip_suffix("ms") {
+        (ms, 0.001)
+    } else if let Some(s) = value.strip_suffix('s') {
+        (s, 1.0)
+    } else {
+        (value, 0.001)
+    };
+
+    let number: f64 = number
+        .trim()
+        .parse()
+        .map_err(|_| format!("Invalid duration: {}", value))?;
+    if !number.is_finite() || number < 0.0 {
+        return Err(format!("Invalid duration: {}", value).into());
+    }
+
+    let duration = Duration::try_from_secs_f64(number * scale)
+        .ok()
+        .filter(|duration| *duration <= MAX_DURATION)
+        .ok_or_else(|| {
+            format!(
+                "Duration {} is longer than the maximum of {}s",
+                value,
+                MAX_DURATION.as_secs()
+            )
+        })?;
+    Ok(duration)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn voice(narrator: &str) -> VoiceSettings {
+        VoiceSettings {
+            narrator: narrator.to_string(),
+            ..VoiceSettings::default()
+        }
+    }
+
+    fn presets() -> HashMap<String, VoicePreset> {
+        let sad = VoiceSettings {
+            emotion: "sad=50".to_string(),
+            pitch: Some("-20".to_string()),
+            ..voice("夏色花梨")
+        };
+        let preset = VoicePreset::from_voice_settings("karin-sad", &sad).unwrap();
+        HashMap::from([(preset.name.clone(), preset)])
+    }
+
+    fn parse(text: &str) -> Result<Vec<Segment>, Box<dyn std::error::Error>> {
+        parse_markup(text, &voice("base"), &presets())
+    }
+
+    #[test]
+    fn parses_duration_units() {
+        assert_eq!(parse_duration("800ms").unwrap(), Duration::from_millis(800));
+        assert_eq!(parse_duration("1.5s").unwrap(), Duration::from_millis(1500));
+        assert_eq!(parse_duration("250").unwrap(), Duration::from_millis(250));
+        assert_eq!(parse_duration("60s").unwrap(), MAX_DURATION);
+    }
+
+    #[test]
+    fn rejects_out_of_range_durations() {
+        for value in ["-1s", "NaN", "inf", "61s", "1e30s", "1e300", "abc", ""] {
+            assert!(parse_duration(value).is_err(), "{} was accepted", value);
+        }
+    }
+
+    #[test]
+    fn pauses_apply_to_the_following_text() {
+        let segments = parse("一つ目。[pause 800ms][pause=1s]二つ目。").unwrap();
+        assert_eq!(segments.len(), 2);
+        assert_eq!(segments[0].pause_before, None);
+        assert_eq!(segments[1].pause_before, Some(Duration::from_millis(1800)));
+    }
+
+    #[test]
+    fn rejects_a_trailing_pause() {
+        assert!(parse("終わり。[pause 1s]").is_err());
+    }
+
+    #[test]
+    fn changes_and_resets_the_voice() {
+        let segments =
+            parse(r#"a[narrator="Some Name" speed=120]b[preset karin-sad]c[reset]d"#).unwrap();
+        let narrators: Vec<&str> = segments.iter().map(|s| s.voice.narrator.as_str()).collect();
+        assert_eq!(narrators, ["base", "Some Name", "夏色花梨", "base"]);
+        assert_eq!(segments[1].voice.speed.as_deref(), Some("120"));
+        assert_eq!(segments[2].voice.emotion, "sad=50");
+        assert_eq!(segments[2].voice.pitch.as_deref(), Some("-20"));
+    }
+
+    #[test]
+    fn keeps_escaped_brackets() {
+        let segments = parse(r"\[注\]本文").unwrap();
+        assert_eq!(segments[0].text, "[注]本文");
+    }
+
+    #[test]
+    fn rejects_invalid_tags() {
+        for text in [
+            "[unclosed",
+            "[]",
+            "[volume=3]",
+            "[preset missing]",
+            "[pause=1s speed=100]",
+            r#"[narrator="open]"#,
+        ] {
+            assert!(parse(text).is_err(), "{} was accepted", text);
+        }
+    }
+}
@@ -0,0 +1,153 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AudioMetadata {
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub comment: Option<String>,
+    /// Source text the audio was synthesized from.
+    pub text: Option<String>,
+}
+
+impl AudioMetadata {
+    fn riff_tags(&self) -> Vec<(&'static [u8; 4], &str)> {
+        let mut tags = Vec::new();
+        if let Some(ref title) = self.title {
+            tags.push((b"INAM", title.as_str()));
+        }
+        if let Some(ref artist) = self.artist {
+            tags.push((b"IART", artist.as_str()));
+        }
+        if let Some(ref comment) = self.comment {
+            tags.push((b"ICMT", comment.as_str()));
+        }
+        if let Some(ref text) = self.text {
+            tags.push((b"ISBJ", text.as_str()));
+        }
+        tags.push((
+            b"ISFT",
+            concat!("voicepeak-cli ", env!("CARGO_PKG_VERSION")),
+        ));
+        tags
+    }
+
+    fn ffmpeg_tags(&self) -> Vec<(&'static str, &str)> {
+        let mut tags = Vec::new();
+        if let Some(ref title) = self.title {
+            tags.push(("title", title.as_str()));
+        }
+        if let Some(ref artist) = self.artist {
+            tags.push(("artist", artist.as_str()));
+        }
+        if let Some(ref comment) = self.comment {
+            tags.push(("comment", comment.as_str()));
+        }
+        if let Some(ref text) = self.text {
+            tags.push(("description", text.as_str()));
+        }
+        tags
+    }
+}
+
+/// Writes tags into `path`: a RIFF INFO chunk for WAV files. For other
+/// extensions the synthesized WAV audio is encoded by ffmpeg into the format
+/// the extension names, with ID3/Vorbis comments.
+pub fn write_metadata(
+    path: &Path,
+    metadata: &AudioMetadata,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let is_wav = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("wav"));
+
+    if is_wav {
+        write_riff_info(path, metadata)
+    } else {
+        write_metadata_with_ffmpeg(path, metadata)
+    }
+}
+
+fn write_riff_info(
+    path: &Path,
+    metadata: &AudioMetadata,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let data = fs::read(path)?;
+    if data.len() < 12 || &data[0..4] != b"RIFF" || &data[8..12] != b"WAVE" {
+        return Err(format!("Not a WAV file: {}", path.display()).into());
+    }
+
+    // Copy every chunk except existing LIST/INFO chunks, which are replaced
+    let mut body = Vec::with_capacity(data.len());
+    body.extend_from_slice(b"WAVE");
+    let mut pos = 12;
+    while pos + 8 <= data.len() {
+        let size = u32::from_le_bytes(data[pos + 4..pos + 8].try_into()?) as usize;
+        let end = (pos + 8 + size + size % 2).min(data.len());
+        let is_info = &data[pos..pos + 4] == b"LIST"
+            && data.get(pos + 8..pos + 12) == Some(b"INFO".as_slice());
+        if !is_info {
+            body.extend_from_slice(&data[pos..end]);
+        }
+        pos = end;
+    }
+
+    let mut info = Vec::new();
+    info.extend_from_slice(b"INFO");
+    for (id, value) in metadata.riff_tags() {
+        let mut bytes = value.as_bytes().to_vec();
+        bytes.push(0);
+        info.extend_from_slice(id);
+        info.extend_from_slice(&(bytes.len() as u32).to_le_bytes());
+        info.extend_from_slice(&bytes);
+        if bytes.len() % 2 == 1 {
+            info.push(0);
+        }
+    }
+    body.extend_from_slice(b"LIST");
+    body.extend_from_slice(&(info.len() as u32).to_le_bytes());
+    body.extend_from_slice(&info);
+
+    let mut output = Vec::with_capacity(body.len() + 8);
+    output.extend_from_slice(b"RIFF");
+    output.extend_from_slice(&(body.len() as u32).to_le_bytes());
+    output.extend_from_slice(&body);
+    fs::write(path, output)?;
+    Ok(())
+}
+
+fn write_metadata_with_ffmpeg(
+    path: &Path,
+    metadata: &AudioMetadata,
+) -> Result<(), Box<dyn std::error::Error>> {
+    // Synthesized audio is always WAV, so it is encoded rather than remuxed;
+    // keeping the extension lets ffmpeg pick the codec and container
+    let file_name = path
+        .file_name()
+        .ok_or("Output path has no file name")?
+        .to_string_lossy();
+    let tagged_path = path.with_file_name(format!(".tagged-{}", file_name));
+
+    let mut command = Command::new("ffmpeg");
+    command.arg("-i").arg(path).arg("-map_metadata").arg("-1");
+    for (key, value) in metadata.ffmpeg_tags() {
+        command.arg("-metadata").arg(format!("{}={}", key, value));
+    }
+    let status = command
+        .arg("-y")
+        .arg("-loglevel")
+        .arg("error")
+        .arg(&tagged_path)
+        .status()?;
+
+    if !status.success() {
+        let _ = fs::remove_file(&tagged_path);
+        return Err("ffmpeg failed to write audio metadata".into());
+    }
+
+    fs::rename(&tagged_path, path)?;
+    Ok(())
+}
@@ -0,0 +1,414 @@
+use regex::{Captures, Regex};
+use serde::{Deserialize, Serialize};
+use std::sync::OnceLock;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UrlMode {
+    Keep,
+    Strip,
+    /// Read the host name only, e.g. "github ドット com".
+    Verbalize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EmojiMode {
+    Keep,
+    Strip,
+    /// Read common emoji by name and strip the rest.
+    Verbalize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NormalizeConfig {
+    pub enabled: bool,
+    /// `2026/10/17` -> `2026年10月17日`
+    pub dates: bool,
+    /// `12:30` -> `12時30分`. Off by default, since scores, ratios and
+    /// timestamps look the same.
+    pub times: bool,
+    /// `¥1,200` -> `1200円`, `$5` -> `5ドル`
+    pub currency: bool,
+    /// `10km` -> `10キロメートル`, `50%` -> `50パーセント`
+    pub units: bool,
+    /// `v0.8.0` -> `バージョン0点8点0`
+    pub versions: bool,
+    /// `1,200,000` -> `1200000`
+    pub numbers: bool,
+    pub urls: UrlMode,
+    pub emoji: EmojiMode,
+}
+
+impl Default for NormalizeConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            dates: true,
+            times: false,
+            currency: true,
+            units: true,
+            versions: true,
+            numbers: true,
+            urls: UrlMode::Verbalize,
+            emoji: EmojiMode::Strip,
+        }
+    }
+}
+
+const UNITS: &[(&str, &str)] = &[
+    ("km/h", "キロメートル毎時"),
+    ("km", "キロメートル"),
+    ("cm", "センチメートル"),
+    ("mm", "ミリメートル"),
+    ("kg", "キログラム"),
+    ("mg", "ミリグラム"),
+    ("ml", "ミリリットル"),
+    ("mL", "ミリリットル"),
+    ("TB", "テラバイト"),
+    ("GB", "ギガバイト"),
+    ("MB", "メガバイト"),
+    ("KB", "キロバイト"),
+    ("kB", "キロバイト"),
+    ("GHz", "ギガヘルツ"),
+    ("MHz", "メガヘルツ"),
+    ("kHz", "キロヘルツ"),
+    ("Hz", "ヘルツ"),
+    ("ms", "ミリ秒"),
+    ("m", "メートル"),
+    ("g", "グラム"),
+    ("L", "リットル"),
+    ("°C", "度"),
+    ("℃", "度"),
+    ("%", "パーセント"),
+    ("％", "パーセント"),
+];
+
+const EMOJI_NAMES: &[(&str, &str)] = &[
+    ("😀", "笑顔"),
+    ("😂", "大笑い"),
+    ("😊", "にっこり"),
+    ("😢", "泣き顔"),
+    ("😭", "大泣き"),
+    ("😡", "怒り"),
+    ("👍", "いいね"),
+    ("👏", "拍手"),
+    ("🙏", "お願い"),
+    ("❤", "ハート"),
+    ("🎉", "おめでとう"),
+    ("🔥", "炎"),
+    ("✅", "チェック"),
+    ("❌", "バツ"),
+    ("⚠", "注意"),
+    ("⭐", "星"),
+];
+
+/// Compiled once, since normalization runs for every chunk.
+struct Patterns {
+    url: Regex,
+    date: Regex,
+    time: Regex,
+    prefixed_version: Regex,
+    version: Regex,
+    yen: Regex,
+    dollar: Regex,
+    euro: Regex,
+    pound: Regex,
+    unit: Regex,
+    grouped_number: Regex,
+}
+
+fn patterns() -> &'static Patterns {
+    static PATTERNS: OnceLock<Patterns> = OnceLock::new();
+    PATTERNS.get_or_init(|| {
+        // Patterns are compile-time constants, so failing to compile is a bug
+        let re = |pattern: &str| Regex::new(pattern).expect("invalid normalization pattern");
+        let units: Vec<String> = UNITS.iter().map(|(unit, _)| regex::escape(unit)).collect();
+
+        Patterns {
+            url: re(r#"https?://[^\s<>"「」（）]+"#),
+            date: re(r"(?-u:\b)(\d{4})[/\-.](\d{1,2})[/\-.](\d{1,2})(?-u:\b)"),
+            time: re(r"(?-u:\b)(\d{1,2}):(\d{2})(?::(\d{2}))?(?-u:\b)"),
+            prefixed_version: re(r"(?-u:\b)[vV](\d+(?:\.\d+)+)(?-u:\b)"),
+            version: re(r"(?-u:\b)(\d+\.\d+\.\d+(?:\.\d+)*)(?-u:\b)"),
+            yen: re(r"[¥￥]\s?(\d[\d,]*(?:\.\d+)?)"),
+            dollar: re(r"[$＄]\s?(\d[\d,]*(?:\.\d+)?)"),
+            euro: re(r"€\s?(\d[\d,]*(?:\.\d+)?)"),
+            pound: re(r"£\s?(\d[\d,]*(?:\.\d+)?)"),
+            unit: re(&format!(r"(\d+(?:\.\d+)?)\s?({})", units.join("|"))),
+            grouped_number: re(r"\d{1,3}(?:,\d{3})+"),
+        }
+    })
+}
+
+/// Rewrites text that VOICEPEAK would otherwise misread, according to the
+/// rules enabled in `config`.
+pub fn normalize_text(text: &str, config: &NormalizeConfig) -> String {
+    if !config.enabled {
+        return text.to_string();
+    }
+    let patterns = patterns();
+
+    // URLs go first so later rules don't rewrite parts of them
+    let mut result = match config.urls {
+        UrlMode::Keep => text.to_string(),
+        UrlMode::Strip => replace(text, &patterns.url, |_| String::new()),
+        UrlMode::Verbalize => replace(text, &patterns.url, |caps| verbalize_url(&caps[0])),
+    };
+
+    result = match config.emoji {
+        EmojiMode::Keep => result,
+        EmojiMode::Strip => strip_emoji(&result, false),
+        EmojiMode::Verbalize => strip_emoji(&result, true),
+    };
+
+    // Dates before versions so `2026.10.17` is read as a date
+    if config.dates {
+        result = replace(&result, &patterns.date, |caps| {
+            format!(
+                "{}年{}月{}日",
+                &caps[1],
+                trim_zeros(&caps[2]),
+                trim_zeros(&caps[3])
+            )
+        });
+    }
+    if config.times {
+        result = replace(&result, &patterns.time, |caps| {
+            let mut time = format!("{}時", trim_zeros(&caps[1]));
+            if &caps[2] != "00" || caps.get(3).is_some() {
+                time.push_str(&format!("{}分", trim_zeros(&caps[2])));
+            }
+            if let Some(seconds) = caps.get(3) {
+                time.push_str(&format!("{}秒", trim_zeros(seconds.as_str())));
+            }
+            time
+        });
+    }
+
+    if config.versions {
+        result = replace(&result, &patterns.prefixed_version, |caps| {
+            format!("バージョン{}", caps[1].replace('.', "点"))
+        });
+        result = replace(&result, &patterns.version, |caps| {
+            if is_ip_address(&caps[1]) {
+                caps[0].to_string()
+            } else {
+                caps[1].replace('.', "点")
+            }
+        });
+    }
+
+    if config.currency {
+        for (pattern, suffix) in [
+            (&patterns.yen, "円"),
+            (&patterns.dollar, "ドル"),
+            (&patterns.euro, "ユーロ"),
+            (&patterns.pound, "ポンド"),
+        ] {
+            result = replace(&result, pattern, |caps| {
+                format!("{}{}", caps[1].replace(',', ""), suffix)
+            });
+        }
+    }
+
+    if config.units {
+        let text = result.clone();
+        result = replace(&text, &patterns.unit, |caps| {
+            let whole = caps.get(0).unwrap();
+            // `A5m`, `mp3g` and `5min` are names or other words, not quantities
+            let before = text[..whole.start()].chars().next_back();
+            let after = text[whole.end()..].chars().next();
+            if before
+                .is_some_and(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-' | '/'))
+                || after.is_some_and(|c| c.is_ascii_alphanumeric())
+            {
+                return whole.as_str().to_string();
+            }
+            let reading = UNITS
+                .iter()
+                .find(|(unit, _)| *unit == &caps[2])
+                .map(|(_, reading)| *reading)
+                .unwrap_or_default();
+            format!("{}{}", &caps[1], reading)
+        });
+    }
+
+    if config.numbers {
+        result = replace(&result, &patterns.grouped_number, |caps| {
+            caps[0].replace(',', "")
+        });
+    }
+
+    result
+}
+
+fn replace(text: &str, re: &Regex, replacer: impl Fn(&Captures) -> String) -> String {
+    re.replace_all(text, |caps: &Captures| replacer(caps))
+        .into_owned()
+}
+
+/// Four dot-separated numbers of at most 255, as in `192.168.0.1`.
+fn is_ip_address(text: &str) -> bool {
+    let parts: Vec<&str> = text.split('.').collect();
+    parts.len() == 4 && parts.iter().all(|part| part.parse::<u8>().is_ok())
+}
+
+fn trim_zeros(number: &str) -> &str {
+    let trimmed = number.trim_start_matches('0');
+    if trimmed.is_empty() {
+        "0"
+    } else {
+        trimmed
+    }
+}
+
+fn verbalize_url(url: &str) -> String {
+    let without_scheme = url.split_once("://").map(|(_, rest)| rest).unwrap_or(url);
+    let host = without_scheme
+        .split(['/', '?', '#', ':'])
+        .next()
+        .unwrap_or_default();
+    let host = host.strip_prefix("www.").unwrap_or(host);
+    host.split('.').collect::<Vec<_>>().join(" ドット ")
+}
+
+/// Pictographs in U+1F000 - U+1FAFF are always emoji. Symbols such as ★, ♪
+/// and ✓ are ordinary in Japanese text, so they only count as emoji when
+/// followed by the emoji variation selector U+FE0F.
+fn is_pictograph(ch: char) -> bool {
+    matches!(ch as u32, 0x1F000..=0x1FAFF)
+}
+
+fn is_symbol(ch: char) -> bool {
+    matches!(ch as u32,
+        0x2600..=0x27BF // misc symbols and dingbats
+        | 0x2B00..=0x2BFF // arrows and stars
+    )
+}
+
+/// Invisible characters that join or style emoji.
+fn is_emoji_modifier(ch: char) -> bool {
+    matches!(ch, '\u{FE0F}' | '\u{200D}')
+}
+
+fn strip_emoji(text: &str, verbalize: bool) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+
+    while let Some(ch) = chars.next() {
+        let emoji = is_pictograph(ch) || (is_symbol(ch) && chars.peek() == Some(&'\u{FE0F}'));
+        if is_emoji_modifier(ch) {
+            continue;
+        }
+        if !emoji {
+            result.push(ch);
+            continue;
+        }
+        if verbalize {
+            let mut buf = [0u8; 4];
+            let ch_str: &str = ch.encode_utf8(&mut buf);
+            if let Some((_, name)) = EMOJI_NAMES.iter().find(|(emoji, _)| *emoji == ch_str) {
+                result.push_str(name);
+            }
+        }
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn normalize(text: &str) -> String {
+        normalize_text(text, &NormalizeConfig::default())
+    }
+
+    #[test]
+    fn reads_urls_by_host() {
+        assert_eq!(
+            normalize("詳細は https://www.github.com/foo を参照"),
+            "詳細は github ドット com を参照"
+        );
+        let config = NormalizeConfig {
+            urls: UrlMode::Strip,
+            ..NormalizeConfig::default()
+        };
+        assert_eq!(normalize_text("見て https://a.example/x", &config), "見て ");
+    }
+
+    #[test]
+    fn strips_emoji_but_keeps_symbols() {
+        assert_eq!(normalize("やった🎉！👍🏻"), "やった！");
+        assert_eq!(normalize("★☆♪✓✔☎⭐"), "★☆♪✓✔☎⭐");
+        assert_eq!(normalize("注意⚠\u{FE0F}"), "注意");
+    }
+
+    #[test]
+    fn verbalizes_known_emoji() {
+        let config = NormalizeConfig {
+            emoji: EmojiMode::Verbalize,
+            ..NormalizeConfig::default()
+        };
+        assert_eq!(normalize_text("最高🔥🦀", &config), "最高炎");
+    }
+
+    #[test]
+    fn reads_dates() {
+        assert_eq!(normalize("2026/01/07に公開"), "2026年1月7日に公開");
+        assert_eq!(normalize("2026.10.17"), "2026年10月17日");
+    }
+
+    #[test]
+    fn reads_times_only_when_enabled() {
+        assert_eq!(normalize("試合は3:00で終了"), "試合は3:00で終了");
+        let config = NormalizeConfig {
+            times: true,
+            ..NormalizeConfig::default()
+        };
+        assert_eq!(normalize_text("12:30に集合", &config), "12時30分に集合");
+        assert_eq!(normalize_text("9:00:05", &config), "9時0分5秒");
+    }
+
+    #[test]
+    fn reads_versions_but_not_ip_addresses() {
+        assert_eq!(normalize("v0.8.0をリリース"), "バージョン0点8点0をリリース");
+        assert_eq!(normalize("1.2.3"), "1点2点3");
+        assert_eq!(normalize("192.168.0.1に接続"), "192.168.0.1に接続");
+    }
+
+    #[test]
+    fn reads_currency() {
+        assert_eq!(normalize("¥1,200"), "1200円");
+        assert_eq!(normalize("$5"), "5ドル");
+        assert_eq!(normalize("€3.50"), "3.50ユーロ");
+        assert_eq!(normalize("£10"), "10ポンド");
+    }
+
+    #[test]
+    fn reads_units_after_numbers_only() {
+        assert_eq!(normalize("10km走った"), "10キロメートル走った");
+        assert_eq!(normalize("約5mと3g"), "約5メートルと3グラム");
+        assert_eq!(normalize("50%"), "50パーセント");
+        assert_eq!(normalize("A5m mp3g 5min"), "A5m mp3g 5min");
+    }
+
+    #[test]
+    fn removes_digit_grouping() {
+        assert_eq!(normalize("1,234,567人"), "1234567人");
+    }
+
+    #[test]
+    fn does_nothing_when_disabled() {
+        let config = NormalizeConfig {
+            enabled: false,
+            ..NormalizeConfig::default()
+        };
+        assert_eq!(
+            normalize_text("v1.2.3 ¥100 10km", &config),
+            "v1.2.3 ¥100 10km"
+        );
+    }
+}
@@ -0,0 +1,59 @@
+use serde::Deserialize;
+use tiny_http::{Method, Request};
+
+use crate::config::VoiceOptions;
+use crate::server::{
+    audio_response, read_json, scale_speed, ApiError, ApiResponse, AudioFormat, ServerState,
+};
+
+/// Body of OpenAI's `POST /v1/audio/speech`. `model` and `instructions` are
+/// accepted and ignored.
+#[derive(Debug, Deserialize)]
+struct SpeechRequest {
+    input: String,
+    /// Preset name, or a speaker label mapped in `[speakers]`.
+    #[serde(default)]
+    voice: Option<String>,
+    /// OpenAI defaults to MP3.
+    #[serde(default = "default_format")]
+    response_format: AudioFormat,
+    /// 0.25 - 4.0, multiplying the preset's speed.
+    #[serde(default)]
+    speed: Option<f64>,
+}
+
+fn default_format() -> AudioFormat {
+    AudioFormat::Mp3
+}
+
+/// Handles the OpenAI speech route. Returns `None` for other paths.
+pub fn handle_openai(
+    state: &ServerState,
+    request: &mut Request,
+    segments: &[&str],
+) -> Option<Result<ApiResponse, ApiError>> {
+    match (request.method(), segments) {
+        (Method::Post, ["v1", "audio", "speech"]) => Some(speech(state, request)),
+        _ => None,
+    }
+}
+
+fn speech(state: &ServerState, request: &mut Request) -> Result<ApiResponse, ApiError> {
+    let speech: SpeechRequest = read_json(request)?;
+    let mut voice = state.resolve_voice(&VoiceOptions {
+        preset: speech.voice.filter(|v| !v.is_empty()),
+        ..VoiceOptions::default()
+    })?;
+    if let Some(speed) = speech.speed {
+        if !(0.25..=4.0).contains(&speed) {
+            return Err(ApiError::bad_request(format!(
+                "speed must be between 0.25 and 4.0: {}",
+                speed
+            )));
+        }
+        scale_speed(&mut voice, speed);
+    }
+
+    let audio = state.synthesize(&speech.input, &voice, speech.response_format)?;
+    Ok(audio_response(audio, speech.response_format))
+}
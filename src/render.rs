@@ -0,0 +1,143 @@
+use std::path::{Path, PathBuf};
+
+use crate::audio::create_temp_audio_file;
+use crate::audio_merge::merge_audio_files_with_gaps;
+use crate::cache::SynthesisCache;
+use crate::config::Config;
+use crate::dictionary::{load_dictionaries, Dictionary};
+use crate::normalize::{normalize_text, NormalizeConfig};
+use crate::segment::{chunk_segments, segment_gaps, Segment};
+use crate::text_splitter::TextSplitter;
+use crate::voicepeak::VoiceSettings;
+
+/// The dictionary, normalization and splitting steps applied to plain text
+/// before synthesis, for callers that render many independent texts.
+pub struct TextPipeline {
+    dictionary: Option<Dictionary>,
+    normalize: Option<NormalizeConfig>,
+    splitter: TextSplitter,
+}
+
+impl TextPipeline {
+    pub fn new(
+        config: &Config,
+        dictionary: bool,
+        normalize: bool,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        Ok(Self {
+            dictionary: if dictionary {
+                Some(load_dictionaries()?)
+            } else {
+                None
+            },
+            normalize: normalize.then(|| config.normalize.clone()),
+            splitter: TextSplitter::from_config(&config.splitter),
+        })
+    }
+
+    /// Splits `text` into chunks to synthesize with `voice`.
+    pub fn chunks(
+        &self,
+        text: &str,
+        voice: &VoiceSettings,
+    ) -> Result<Vec<Segment>, Box<dyn std::error::Error>> {
+        // Normalization first, so dictionary output is read as written
+        let mut segment = Segment::new(text, voice);
+        if let Some(normalize) = &self.normalize {
+            segment.text = normalize_text(&segment.text, normalize);
+        }
+        if let Some(dictionary) = &self.dictionary {
+            segment.text = dictionary.apply(&segment.text, &voice.narrator)?;
+        }
+
+        let chunks = chunk_segments(&[segment], &self.splitter);
+        if chunks.is_empty() {
+            return Err("Text is empty".into());
+        }
+        Ok(chunks)
+    }
+}
+
+/// Synthesizes `chunk` into `output`, reusing cached audio when available.
+pub fn synthesize_chunk(
+    chunk: &Segment,
+    output: &Path,
+    verbose: bool,
+    cache: Option<&SynthesisCache>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let Some(cache) = cache else {
+        return chunk
+            .voice
+            .command(&chunk.text, output)
+            .execute_with_verbose(verbose);
+    };
+
+    let key = cache.key(&chunk.text, &chunk.voice);
+    // A broken cache should never stop synthesis
+    match cache.get(&key, output) {
+        Ok(true) => {
+            if verbose {
+                println!("Using cached audio {}", key);
+            }
+            return Ok(());
+        }
+        Ok(false) => {}
+        Err(e) => eprintln!("Warning: failed to read synthesis cache: {}", e),
+    }
+
+    chunk
+        .voice
+        .command(&chunk.text, output)
+        .execute_with_verbose(verbose)?;
+    if let Err(e) = cache.put(&key, output) {
+        eprintln!("Warning: failed to write synthesis cache: {}", e);
+    }
+    Ok(())
+}
+
+/// Synthesizes `chunks` and writes them merged into `output`.
+pub fn render_to_file(
+    chunks: &[Segment],
+    output: &Path,
+    verbose: bool,
+    cache: Option<&SynthesisCache>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if let Some(parent) = output.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    let temp_files = generate_chunks(chunks, verbose, cache)?;
+    let temp_paths: Vec<&Path> = temp_files.iter().map(|p| p.as_path()).collect();
+    let result = merge_audio_files_with_gaps(&temp_paths, &segment_gaps(chunks), output);
+
+    for temp_file in temp_files {
+        let _ = std::fs::remove_file(temp_file);
+    }
+    result
+}
+
+pub fn generate_chunks(
+    chunks: &[Segment],
+    verbose: bool,
+    cache: Option<&SynthesisCache>,
+) -> Result<Vec<PathBuf>, Box<dyn std::error::Error>> {
+    let mut temp_files = Vec::new();
+
+    for (i, chunk) in chunks.iter().enumerate() {
+        if chunks.len() > 1 {
+            println!("Generating part {}/{}", i + 1, chunks.len());
+        }
+
+        let temp_path = create_temp_audio_file()?;
+        if let Err(e) = synthesize_chunk(chunk, &temp_path, verbose, cache) {
+            let _ = std::fs::remove_file(&temp_path);
+            for temp_file in temp_files {
+                let _ = std::fs::remove_file(temp_file);
+            }
+            return Err(e);
+        }
+        temp_files.push(temp_path);
+    }
+
+    Ok(temp_files)
+}
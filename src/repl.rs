@@ -0,0 +1,185 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use crate::config::VoicePreset;
+
+pub const REPL_HELP: &str = "\
+Type text to speak it. Commands:
+  :preset NAME      Use a preset (clears narrator, emotion, speed and pitch)
+  :narrator NAME    Set the narrator
+  :emotion EXPR     Set the emotion (e.g., happy=30,sad=10)
+  :speed VALUE      Set the speed (50 - 200)
+  :pitch VALUE      Set the pitch (-300 - 300)
+  :save NAME        Save the current voice as a preset (keeps its playback settings)
+  :replay           Speak the last line again with the current settings
+  :show             Show the current settings
+  :help             Show this help
+  :quit             Exit
+Run a setting command without a value to clear it. Start a line with :: to
+speak text beginning with a colon.";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReplCommand {
+    Speak(String),
+    Preset(Option<String>),
+    Narrator(Option<String>),
+    Emotion(Option<String>),
+    Speed(Option<String>),
+    Pitch(Option<String>),
+    Save(String),
+    Replay,
+    Show,
+    Help,
+    Quit,
+}
+
+/// Voice settings changed by REPL commands, applied on top of the config
+/// the same way command-line options are.
+#[derive(Debug, Clone, Default)]
+pub struct ReplState {
+    pub preset: Option<String>,
+    pub narrator: Option<String>,
+    pub emotion: Option<String>,
+    pub speed: Option<String>,
+    pub pitch: Option<String>,
+    pub last_text: Option<String>,
+}
+
+/// Parses one line of REPL input. Returns `None` for blank lines.
+pub fn parse_repl_line(line: &str) -> Result<Option<ReplCommand>, String> {
+    let line = line.trim();
+    if line.is_empty() {
+        return Ok(None);
+    }
+    if let Some(text) = line.strip_prefix("::") {
+        return Ok(Some(ReplCommand::Speak(format!(":{}", text))));
+    }
+    let Some(command) = line.strip_prefix(':') else {
+        return Ok(Some(ReplCommand::Speak(line.to_string())));
+    };
+
+    let (name, argument) = match command.split_once(char::is_whitespace) {
+        Some((name, argument)) => (name, Some(argument.trim().to_string())),
+        None => (command, None),
+    };
+    let number = |argument: Option<String>| match argument {
+        Some(value) if value.parse::<i32>().is_err() => {
+            Err(format!("Expected a number for :{}: {}", name, value))
+        }
+        _ => Ok(argument),
+    };
+
+    let command = match name {
+        "preset" | "p" => ReplCommand::Preset(argument),
+        "narrator" | "n" => ReplCommand::Narrator(argument),
+        "emotion" | "e" => ReplCommand::Emotion(argument),
+        "speed" => ReplCommand::Speed(number(argument)?),
+        "pitch" => ReplCommand::Pitch(number(argument)?),
+        "save" => ReplCommand::Save(argument.ok_or(":save needs a preset name")?),
+        "replay" | "r" => ReplCommand::Replay,
+        "show" => ReplCommand::Show,
+        "help" | "h" | "?" => ReplCommand::Help,
+        "quit" | "q" | "exit" => ReplCommand::Quit,
+        _ => return Err(format!("Unknown command: :{} (type :help)", name)),
+    };
+    Ok(Some(command))
+}
+
+impl ReplState {
+    /// Applies a setting command. Returns `false` for commands that are not
+    /// settings.
+    pub fn apply(&mut self, command: &ReplCommand) -> bool {
+        match command {
+            ReplCommand::Preset(preset) => {
+                *self = Self {
+                    preset: preset.clone(),
+                    last_text: self.last_text.take(),
+                    ..Self::default()
+                };
+            }
+            ReplCommand::Narrator(narrator) => self.narrator = narrator.clone(),
+            ReplCommand::Emotion(emotion) => self.emotion = emotion.clone(),
+            ReplCommand::Speed(speed) => self.speed = speed.clone(),
+            ReplCommand::Pitch(pitch) => self.pitch = pitch.clone(),
+            _ => return false,
+        }
+        true
+    }
+
+    /// Command-line arguments that speak `text` with the current settings.
+    ///
+    /// `--preset` cannot be combined with `--narrator` or `--emotion`, so a
+    /// preset with either override is expanded into explicit options.
+    pub fn args(&self, text: &str, presets: &HashMap<String, VoicePreset>) -> Vec<String> {
+        let mut args = vec!["vp".to_string()];
+        let mut push = |name: &str, value: Option<String>| {
+            // `--name=value` keeps negative pitches from being read as flags
+            if let Some(value) = value {
+                args.push(format!("--{}={}", name, value));
+            }
+        };
+
+        let preset = self.preset.as_ref().and_then(|name| presets.get(name));
+        match preset {
+            Some(preset) if self.narrator.is_some() || self.emotion.is_some() => {
+                push(
+                    "narrator",
+                    self.narrator.clone().or(Some(preset.narrator.clone())),
+                );
+                push(
+                    "emotion",
+                    self.emotion
+                        .clone()
+                        .or(Some(preset.get_emotion_string()))
+                        .filter(|e| !e.is_empty()),
+                );
+                push(
+                    "speed",
+                    self.speed.clone().or(preset.speed.map(|s| s.to_string())),
+                );
+                push(
+                    "pitch",
+                    self.pitch.clone().or(preset.pitch.map(|p| p.to_string())),
+                );
+            }
+            _ => {
+                // An unknown preset is passed through so the usual error is shown
+                push("preset", self.preset.clone());
+                push("narrator", self.narrator.clone());
+                push("emotion", self.emotion.clone());
+                push("speed", self.speed.clone());
+                push("pitch", self.pitch.clone());
+            }
+        }
+
+        // Streaming starts playback as soon as the first chunk is ready
+        args.extend(["--playback-mode".to_string(), "stream".to_string()]);
+        args.push("--".to_string());
+        args.push(text.to_string());
+        args
+    }
+
+    pub fn describe(&self) -> String {
+        let settings: Vec<String> = [
+            ("preset", &self.preset),
+            ("narrator", &self.narrator),
+            ("emotion", &self.emotion),
+            ("speed", &self.speed),
+            ("pitch", &self.pitch),
+        ]
+        .iter()
+        .filter_map(|(name, value)| value.as_ref().map(|v| format!("{}={}", name, v)))
+        .collect();
+
+        if settings.is_empty() {
+            "(config defaults)".to_string()
+        } else {
+            settings.join(" ")
+        }
+    }
+}
+
+pub fn get_history_path() -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let data_dir = dirs::data_local_dir().ok_or("Could not determine data directory")?;
+    Ok(data_dir.join("vp").join("repl_history.txt"))
+}
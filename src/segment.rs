@@ -0,0 +1,78 @@
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use crate::audio_merge::CHUNK_GAP;
+use crate::text_splitter::{Boundary, TextSplitter};
+use crate::voicepeak::VoiceSettings;
+
+/// A piece of text synthesized with a single set of voice parameters.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Segment {
+    pub text: String,
+    pub voice: VoiceSettings,
+    /// Silence before this segment; `None` uses the default gap between chunks.
+    pub pause_before: Option<Duration>,
+}
+
+impl Segment {
+    pub fn new(text: &str, voice: &VoiceSettings) -> Self {
+        Self {
+            text: text.to_string(),
+            voice: voice.clone(),
+            pause_before: None,
+        }
+    }
+}
+
+/// Splits every segment with `splitter`. The first chunk of a segment keeps
+/// its pause; the rest use the default gap.
+pub fn chunk_segments(segments: &[Segment], splitter: &TextSplitter) -> Vec<Segment> {
+    chunk_segments_with_boundaries(segments, splitter)
+        .into_iter()
+        .map(|(chunk, _)| chunk)
+        .collect()
+}
+
+/// Like [`chunk_segments`], also reporting why each chunk ends.
+pub fn chunk_segments_with_boundaries(
+    segments: &[Segment],
+    splitter: &TextSplitter,
+) -> Vec<(Segment, Boundary)> {
+    let mut chunks = Vec::new();
+
+    for segment in segments {
+        for (i, (text, boundary)) in splitter
+            .split_with_boundaries(&segment.text)
+            .into_iter()
+            .enumerate()
+        {
+            if text.trim().is_empty() {
+                continue;
+            }
+            chunks.push((
+                Segment {
+                    text,
+                    voice: segment.voice.clone(),
+                    pause_before: if i == 0 { segment.pause_before } else { None },
+                },
+                boundary,
+            ));
+        }
+    }
+
+    chunks
+}
+
+/// Silence to insert before each chunk when merging. Leading silence is only
+/// inserted when explicitly requested.
+pub fn segment_gaps(chunks: &[Segment]) -> Vec<Duration> {
+    chunks
+        .iter()
+        .enumerate()
+        .map(|(i, chunk)| match chunk.pause_before {
+            Some(pause) => pause,
+            None if i == 0 => Duration::ZERO,
+            None => CHUNK_GAP,
+        })
+        .collect()
+}
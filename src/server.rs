@@ -0,0 +1,451 @@
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::{Cursor, Read};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc};
+use std::thread;
+use tiny_http::{Header, Method, Request, Response, Server};
+
+use crate::audio::{create_temp_audio_file, play_audio_and_cleanup_with_options, PlaybackOptions};
+use crate::audio_merge::{convert_audio, convert_to_pcm};
+use crate::cache::SynthesisCache;
+use crate::config::{get_presets_map, Config, VoiceOptions, VoicePreset};
+use crate::openai::handle_openai;
+use crate::render::{render_to_file, TextPipeline};
+use crate::segment::Segment;
+use crate::voicepeak::{emotions, narrators, VoiceSettings};
+use crate::voicevox::handle_voicevox;
+
+pub const DEFAULT_LISTEN_ADDRESS: &str = "127.0.0.1:8765";
+
+/// Requests are handled concurrently; synthesis itself is serialized by the
+/// VOICEPEAK lock.
+const WORKER_THREADS: usize = 4;
+
+const MAX_BODY_BYTES: u64 = 1024 * 1024;
+
+/// Sample rate of `pcm` output, as in OpenAI's speech API.
+pub const PCM_SAMPLE_RATE: u32 = 24000;
+
+pub type ApiResponse = Response<Cursor<Vec<u8>>>;
+
+#[derive(Debug)]
+pub struct ApiError {
+    pub status: u16,
+    pub message: String,
+}
+
+impl ApiError {
+    pub fn bad_request(message: impl ToString) -> Self {
+        Self {
+            status: 400,
+            message: message.to_string(),
+        }
+    }
+
+    pub fn not_found(message: impl ToString) -> Self {
+        Self {
+            status: 404,
+            message: message.to_string(),
+        }
+    }
+}
+
+impl From<Box<dyn std::error::Error>> for ApiError {
+    fn from(e: Box<dyn std::error::Error>) -> Self {
+        Self {
+            status: 500,
+            message: e.to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AudioFormat {
+    #[default]
+    Wav,
+    Mp3,
+    Opus,
+    Aac,
+    Flac,
+    /// Raw 16-bit little-endian mono samples at `PCM_SAMPLE_RATE`.
+    Pcm,
+}
+
+impl AudioFormat {
+    pub fn content_type(self) -> &'static str {
+        match self {
+            AudioFormat::Wav => "audio/wav",
+            AudioFormat::Mp3 => "audio/mpeg",
+            AudioFormat::Opus => "audio/ogg",
+            AudioFormat::Aac => "audio/aac",
+            AudioFormat::Flac => "audio/flac",
+            AudioFormat::Pcm => "audio/pcm",
+        }
+    }
+
+    fn extension(self) -> &'static str {
+        match self {
+            AudioFormat::Wav => "wav",
+            AudioFormat::Mp3 => "mp3",
+            AudioFormat::Opus => "opus",
+            AudioFormat::Aac => "aac",
+            AudioFormat::Flac => "flac",
+            AudioFormat::Pcm => "pcm",
+        }
+    }
+}
+
+/// Third-party APIs served alongside the native one on request. OpenAI's
+/// `/v1/audio/speech` fits the native routes and is always served.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompatApi {
+    Voicevox,
+}
+
+impl CompatApi {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "voicevox" => Some(CompatApi::Voicevox),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct SpeechRequest {
+    text: String,
+    #[serde(flatten)]
+    voice: VoiceOptions,
+    #[serde(default)]
+    format: AudioFormat,
+}
+
+#[derive(Serialize)]
+struct PresetList<'a> {
+    default_preset: Option<&'a str>,
+    presets: &'a [VoicePreset],
+}
+
+struct PlaybackRequest {
+    chunks: Vec<Segment>,
+    playback: PlaybackOptions,
+}
+
+/// Shared by all request handlers.
+pub struct ServerState {
+    pub config: Config,
+    pub pipeline: TextPipeline,
+    pub cache: Option<SynthesisCache>,
+    pub verbose: bool,
+    pub compat: Vec<CompatApi>,
+    playback: mpsc::Sender<PlaybackRequest>,
+    queued: Arc<AtomicUsize>,
+}
+
+impl ServerState {
+    /// Creates the state and starts the thread that plays queued speech in
+    /// order.
+    pub fn new(
+        config: Config,
+        pipeline: TextPipeline,
+        cache: Option<SynthesisCache>,
+        verbose: bool,
+    ) -> Self {
+        let (sender, receiver) = mpsc::channel::<PlaybackRequest>();
+        let queued = Arc::new(AtomicUsize::new(0));
+
+        let player_cache = cache.clone();
+        let player_queued = Arc::clone(&queued);
+        thread::spawn(move || {
+            for request in receiver {
+                let result = create_temp_audio_file().and_then(|temp_path| {
+                    render_to_file(&request.chunks, &temp_path, verbose, player_cache.as_ref())
+                        .and_then(|_| {
+                            play_audio_and_cleanup_with_options(&temp_path, &request.playback)
+                        })
+                        .inspect_err(|_| {
+                            let _ = std::fs::remove_file(&temp_path);
+                        })
+                });
+                if let Err(e) = result {
+                    eprintln!("Playback failed: {}", e);
+                }
+                player_queued.fetch_sub(1, Ordering::SeqCst);
+            }
+        });
+
+        Self {
+            config,
+            pipeline,
+            cache,
+            verbose,
+            compat: Vec::new(),
+            playback: sender,
+            queued,
+        }
+    }
+
+    pub fn compat(mut self, compat: Vec<CompatApi>) -> Self {
+        self.compat = compat;
+        self
+    }
+
+    pub fn resolve_voice(&self, options: &VoiceOptions) -> Result<VoiceSettings, ApiError> {
+        options.resolve(&self.config).map_err(ApiError::bad_request)
+    }
+
+    /// Synthesizes `text` and returns the encoded audio.
+    pub fn synthesize(
+        &self,
+        text: &str,
+        voice: &VoiceSettings,
+        format: AudioFormat,
+    ) -> Result<Vec<u8>, ApiError> {
+        let chunks = self
+            .pipeline
+            .chunks(text, voice)
+            .map_err(ApiError::bad_request)?;
+
+        let wav_path = create_temp_audio_file()?;
+        let result = render_to_file(&chunks, &wav_path, self.verbose, self.cache.as_ref())
+            .and_then(|_| match format {
+                AudioFormat::Wav => Ok(std::fs::read(&wav_path)?),
+                _ => {
+                    let encoded_path = wav_path.with_extension(format.extension());
+                    let converted = match format {
+                        AudioFormat::Pcm => {
+                            convert_to_pcm(&wav_path, &encoded_path, PCM_SAMPLE_RATE)
+                        }
+                        _ => convert_audio(&wav_path, &encoded_path),
+                    };
+                    let encoded = converted.and_then(|_| Ok(std::fs::read(&encoded_path)?));
+                    let _ = std::fs::remove_file(&encoded_path);
+                    encoded
+                }
+            });
+        let _ = std::fs::remove_file(&wav_path);
+
+        Ok(result?)
+    }
+
+    /// Queues `text` for playback on this machine. Returns the number of
+    /// queued requests including this one.
+    pub fn queue_playback(&self, text: &str, options: &VoiceOptions) -> Result<usize, ApiError> {
+        let voice = self.resolve_voice(options)?;
+        let chunks = self
+            .pipeline
+            .chunks(text, &voice)
+            .map_err(ApiError::bad_request)?;
+
+        // Playback settings come from the preset, as for `vp -p`
+        let presets = get_presets_map(&self.config);
+        let preset = options
+            .preset
+            .as_ref()
+            .or(self.config.default_preset.as_ref())
+            .and_then(|name| presets.get(name));
+        let playback = PlaybackOptions {
+            device: preset.and_then(|p| p.device.clone()),
+            volume: preset.and_then(|p| p.volume),
+            rate: preset.and_then(|p| p.playback_rate),
+        };
+
+        let position = self.queued.fetch_add(1, Ordering::SeqCst) + 1;
+        if self
+            .playback
+            .send(PlaybackRequest { chunks, playback })
+            .is_err()
+        {
+            self.queued.fetch_sub(1, Ordering::SeqCst);
+            return Err(ApiError {
+                status: 500,
+                message: "Playback thread has stopped".to_string(),
+            });
+        }
+        Ok(position)
+    }
+}
+
+/// Multiplies the voice's speed (100 when unset) by `scale`, clamped to
+/// VOICEPEAK's 50 - 200 range.
+pub fn scale_speed(voice: &mut VoiceSettings, scale: f64) {
+    let base = voice
+        .speed
+        .as_deref()
+        .and_then(|v| v.parse::<i32>().ok())
+        .unwrap_or(100);
+    let speed = (base as f64 * scale).round() as i32;
+    voice.speed = Some(speed.clamp(50, 200).to_string());
+}
+
+pub fn json_response<T: Serialize>(status: u16, value: &T) -> ApiResponse {
+    let body = serde_json::to_vec(value).unwrap_or_default();
+    with_content_type(Response::from_data(body), "application/json").with_status_code(status)
+}
+
+pub fn audio_response(audio: Vec<u8>, format: AudioFormat) -> ApiResponse {
+    with_content_type(Response::from_data(audio), format.content_type())
+}
+
+pub fn with_content_type(response: ApiResponse, content_type: &str) -> ApiResponse {
+    match Header::from_bytes(&b"Content-Type"[..], content_type.as_bytes()) {
+        Ok(header) => response.with_header(header),
+        Err(_) => response,
+    }
+}
+
+/// Reads and parses a JSON request body.
+pub fn read_json<T: DeserializeOwned>(request: &mut Request) -> Result<T, ApiError> {
+    let body = read_body(request)?;
+    serde_json::from_slice(&body).map_err(|e| ApiError::bad_request(format!("Invalid JSON: {}", e)))
+}
+
+pub fn read_body(request: &mut Request) -> Result<Vec<u8>, ApiError> {
+    let mut body = Vec::new();
+    request
+        .as_reader()
+        .take(MAX_BODY_BYTES + 1)
+        .read_to_end(&mut body)
+        .map_err(|e| ApiError::bad_request(format!("Failed to read request body: {}", e)))?;
+    if body.len() as u64 > MAX_BODY_BYTES {
+        return Err(ApiError {
+            status: 413,
+            message: "Request body is too large".to_string(),
+        });
+    }
+    Ok(body)
+}
+
+/// Decodes `%XX` escapes in a URL path segment or query value.
+pub fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// Parses the query string of `url`. `+` is read as a space.
+pub fn query_params(url: &str) -> HashMap<String, String> {
+    let Some((_, query)) = url.split_once('?') else {
+        return HashMap::new();
+    };
+    query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+            (
+                percent_decode(&name.replace('+', " ")),
+                percent_decode(&value.replace('+', " ")),
+            )
+        })
+        .collect()
+}
+
+fn handle(state: &ServerState, request: &mut Request) -> Result<ApiResponse, ApiError> {
+    let url = request.url().to_string();
+    let path = url.split('?').next().unwrap_or_default();
+    let segments: Vec<String> = path
+        .trim_matches('/')
+        .split('/')
+        .map(percent_decode)
+        .collect();
+    let segments: Vec<&str> = segments.iter().map(|s| s.as_str()).collect();
+    let query = query_params(&url);
+
+    if let Some(response) = handle_openai(state, request, &segments) {
+        return response;
+    }
+    if state.compat.contains(&CompatApi::Voicevox) {
+        if let Some(response) = handle_voicevox(state, request, &segments, &query) {
+            return response;
+        }
+    }
+
+    match (request.method(), segments.as_slice()) {
+        (Method::Get, ["health"]) => Ok(json_response(
+            200,
+            &serde_json::json!({ "status": "ok", "version": env!("CARGO_PKG_VERSION") }),
+        )),
+        (Method::Get, ["v1", "narrators"]) => Ok(json_response(200, &narrators()?)),
+        (Method::Get, ["v1", "narrators", narrator, "emotions"]) => {
+            Ok(json_response(200, &emotions(narrator)?))
+        }
+        (Method::Get, ["v1", "presets"]) => Ok(json_response(
+            200,
+            &PresetList {
+                default_preset: state.config.default_preset.as_deref(),
+                presets: &state.config.presets,
+            },
+        )),
+        (Method::Post, ["v1", "synthesize"]) => {
+            let speech: SpeechRequest = read_json(request)?;
+            let voice = state.resolve_voice(&speech.voice)?;
+            let audio = state.synthesize(&speech.text, &voice, speech.format)?;
+            Ok(audio_response(audio, speech.format))
+        }
+        (Method::Post, ["v1", "play"]) => {
+            let speech: SpeechRequest = read_json(request)?;
+            let queued = state.queue_playback(&speech.text, &speech.voice)?;
+            Ok(json_response(202, &serde_json::json!({ "queued": queued })))
+        }
+        (method, _) => Err(ApiError::not_found(format!(
+            "No route for {} {}",
+            method, path
+        ))),
+    }
+}
+
+/// Serves the API on `address` until the process is stopped.
+pub fn serve(address: &str, state: ServerState) -> Result<(), Box<dyn std::error::Error>> {
+    let server =
+        Server::http(address).map_err(|e| format!("Failed to listen on {}: {}", address, e))?;
+    println!("Listening on http://{}", address);
+
+    let server = Arc::new(server);
+    let state = Arc::new(state);
+    let workers: Vec<_> = (0..WORKER_THREADS)
+        .map(|_| {
+            let server = Arc::clone(&server);
+            let state = Arc::clone(&state);
+            thread::spawn(move || {
+                for mut request in server.incoming_requests() {
+                    let response = handle(&state, &mut request).unwrap_or_else(|e| {
+                        json_response(e.status, &serde_json::json!({ "error": e.message }))
+                    });
+                    let status = response.status_code().0;
+                    if state.verbose || status >= 400 {
+                        println!("{} {} -> {}", request.method(), request.url(), status);
+                    }
+                    if let Err(e) = request.respond(response) {
+                        eprintln!("Failed to send response: {}", e);
+                    }
+                }
+            })
+        })
+        .collect();
+
+    for worker in workers {
+        let _ = worker.join();
+    }
+    Ok(())
+}
@@ -0,0 +1,555 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use crate::config::VoicePreset;
+use crate::markup::parse_duration;
+use crate::segment::Segment;
+use crate::text_util::{decode_entities, find_tag_end, is_cjk};
+use crate::voicepeak::VoiceSettings;
+
+const SENTENCE_PAUSE: Duration = Duration::from_millis(400);
+const PARAGRAPH_PAUSE: Duration = Duration::from_millis(1000);
+
+const MIN_SPEED: i32 = 50;
+const MAX_SPEED: i32 = 200;
+const MIN_PITCH: i32 = -300;
+const MAX_PITCH: i32 = 300;
+
+pub fn looks_like_ssml(text: &str) -> bool {
+    let text = text.trim_start();
+    let text = match text.strip_prefix("<?xml") {
+        Some(rest) => rest
+            .split_once("?>")
+            .map(|(_, body)| body.trim_start())
+            .unwrap_or(rest),
+        None => text,
+    };
+    text.starts_with("<speak")
+}
+
+/// Parses a practical SSML subset into segments:
+/// `<break>`, `<prosody rate pitch>`, `<voice name>`, `<say-as>`, `<sub alias>`,
+/// `<p>` and `<s>`. Other elements are ignored but their text is kept.
+///
+/// `<voice name>` selects a preset when one matches, otherwise a narrator
+/// with the enclosing voice's emotion, speed and pitch. Pitch in Hz cannot be
+/// mapped onto VOICEPEAK's pitch and is ignored with a warning.
+pub fn parse_ssml(
+    text: &str,
+    base_voice: &VoiceSettings,
+    presets: &HashMap<String, VoicePreset>,
+) -> Result<Vec<Segment>, Box<dyn std::error::Error>> {
+    let mut parser = SsmlParser {
+        segments: Vec::new(),
+        voice: base_voice.clone(),
+        current_text: String::new(),
+        pending_pause: None,
+        frames: Vec::new(),
+    };
+
+    for node in tokenize(text)? {
+        match node {
+            Node::Text(content) => parser.push_text(&content),
+            Node::Start { name, attrs } => parser.start(&name, &attrs, presets)?,
+            Node::Empty { name, attrs } => {
+                parser.start(&name, &attrs, presets)?;
+                parser.end(&name)?;
+            }
+            Node::End { name } => parser.end(&name)?,
+        }
+    }
+
+    if let Some(frame) = parser.frames.last() {
+        return Err(format!("Unclosed SSML element: <{}>", frame.name).into());
+    }
+
+    parser.flush();
+    Ok(parser.segments)
+}
+
+enum FrameKind {
+    Voice(VoiceSettings),
+    SayAs(String),
+    Sub,
+    Plain,
+}
+
+struct Frame {
+    name: String,
+    kind: FrameKind,
+}
+
+struct SsmlParser {
+    segments: Vec<Segment>,
+    voice: VoiceSettings,
+    current_text: String,
+    pending_pause: Option<Duration>,
+    frames: Vec<Frame>,
+}
+
+impl SsmlParser {
+    fn push_text(&mut self, content: &str) {
+        // Text inside <sub> is replaced by its alias
+        if self.frames.iter().any(|f| matches!(f.kind, FrameKind::Sub)) {
+            return;
+        }
+
+        let say_as = self.frames.iter().rev().find_map(|f| match f.kind {
+            FrameKind::SayAs(ref interpret_as) => Some(interpret_as.as_str()),
+            _ => None,
+        });
+        match say_as {
+            Some("characters" | "spell-out" | "digits") => {
+                let spelled: Vec<String> = content
+                    .chars()
+                    .filter(|c| !c.is_whitespace())
+                    .map(|c| c.to_string())
+                    .collect();
+                self.current_text.push_str(&spelled.join(" "));
+            }
+            _ => self.current_text.push_str(content),
+        }
+    }
+
+    fn start(
+        &mut self,
+        name: &str,
+        attrs: &HashMap<String, String>,
+        presets: &HashMap<String, VoicePreset>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let kind = match name {
+            "break" => {
+                self.flush();
+                let pause = break_duration(attrs)?;
+                self.pending_pause = Some(self.pending_pause.unwrap_or_default() + pause);
+                FrameKind::Plain
+            }
+            "p" => {
+                self.boundary(PARAGRAPH_PAUSE);
+                FrameKind::Plain
+            }
+            "s" => {
+                self.boundary(SENTENCE_PAUSE);
+                FrameKind::Plain
+            }
+            "voice" => {
+                self.boundary(Duration::ZERO);
+                let previous = self.voice.clone();
+                if let Some(voice_name) = attrs.get("name") {
+                    self.voice = match presets.get(voice_name) {
+                        Some(preset) => preset.voice_settings(),
+                        None => VoiceSettings {
+                            narrator: voice_name.clone(),
+                            ..self.voice.clone()
+                        },
+                    };
+                }
+                FrameKind::Voice(previous)
+            }
+            "prosody" => {
+                self.boundary(Duration::ZERO);
+                let previous = self.voice.clone();
+                if let Some(rate) = attrs.get("rate") {
+                    let current = self.voice.speed.as_deref().and_then(|s| s.parse().ok());
+                    self.voice.speed =
+                        Some(prosody_rate(rate, current.unwrap_or(100))?.to_string());
+                }
+                if let Some(pitch) = attrs.get("pitch").filter(|pitch| {
+                    let hertz = pitch.to_ascii_lowercase().ends_with("hz");
+                    if hertz {
+                        eprintln!("Warning: prosody pitch in Hz is not supported: {}", pitch);
+                    }
+                    !hertz
+                }) {
+                    let current = self.voice.pitch.as_deref().and_then(|p| p.parse().ok());
+                    self.voice.pitch =
+                        Some(prosody_pitch(pitch, current.unwrap_or(0))?.to_string());
+                }
+                FrameKind::Voice(previous)
+            }
+            "say-as" => FrameKind::SayAs(attrs.get("interpret-as").cloned().unwrap_or_default()),
+            "sub" => {
+                if let Some(alias) = attrs.get("alias") {
+                    self.current_text.push_str(alias);
+                }
+                FrameKind::Sub
+            }
+            _ => FrameKind::Plain,
+        };
+
+        self.frames.push(Frame {
+            name: name.to_string(),
+            kind,
+        });
+        Ok(())
+    }
+
+    fn end(&mut self, name: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let frame = self
+            .frames
+            .pop()
+            .ok_or_else(|| format!("Unexpected closing tag: </{}>", name))?;
+        if frame.name != name {
+            return Err(format!(
+                "Mismatched SSML tags: <{}> closed by </{}>",
+                frame.name, name
+            )
+            .into());
+        }
+
+        match (name, frame.kind) {
+            ("p", _) => self.boundary(PARAGRAPH_PAUSE),
+            ("s", _) => self.boundary(SENTENCE_PAUSE),
+            (_, FrameKind::Voice(previous)) => {
+                self.boundary(Duration::ZERO);
+                self.voice = previous;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    // Structural boundaries only add silence once something has been spoken,
+    // so a document never starts with an implicit pause.
+    fn boundary(&mut self, pause: Duration) {
+        self.flush();
+        if !self.segments.is_empty() {
+            self.pending_pause = Some(self.pending_pause.unwrap_or_default().max(pause));
+        }
+    }
+
+    fn flush(&mut self) {
+        let text = normalize_whitespace(&self.current_text);
+        self.current_text.clear();
+        if text.is_empty() {
+            return;
+        }
+
+        self.segments.push(Segment {
+            text,
+            voice: self.voice.clone(),
+            pause_before: self.pending_pause.take(),
+        });
+    }
+}
+
+// Line breaks in the markup are not spaces in Japanese text, so words are
+// only joined with a space when neither side is CJK
+fn normalize_whitespace(text: &str) -> String {
+    let mut joined = String::new();
+    for word in text.split_whitespace() {
+        if let (Some(prev), Some(next)) = (joined.chars().last(), word.chars().next()) {
+            if !is_cjk(prev) && !is_cjk(next) {
+                joined.push(' ');
+            }
+        }
+        joined.push_str(word);
+    }
+    joined
+}
+
+fn break_duration(attrs: &HashMap<String, String>) -> Result<Duration, Box<dyn std::error::Error>> {
+    if let Some(time) = attrs.get("time") {
+        return parse_duration(time);
+    }
+
+    let millis = match attrs.get("strength").map(|s| s.as_str()) {
+        Some("none") => 0,
+        Some("x-weak") => 100,
+        Some("weak") => 250,
+        Some("medium") | None => 500,
+        Some("strong") => 1000,
+        Some("x-strong") => 2000,
+        Some(other) => return Err(format!("Invalid break strength: {}", other).into()),
+    };
+    Ok(Duration::from_millis(millis))
+}
+
+/// Maps an SSML rate onto VOICEPEAK speed (100 = normal), relative to `current`.
+fn prosody_rate(rate: &str, current: i32) -> Result<i32, Box<dyn std::error::Error>> {
+    let factor = match rate {
+        "x-slow" => 0.5,
+        "slow" => 0.75,
+        "medium" | "default" => 1.0,
+        "fast" => 1.25,
+        "x-fast" => 1.75,
+        _ => {
+            if let Some(percent) = rate.strip_suffix('%') {
+                let percent: f64 = percent
+                    .parse()
+                    .map_err(|_| format!("Invalid prosody rate: {}", rate))?;
+                // "+20%" / "-20%" are relative changes, "120%" is an absolute ratio
+                if rate.starts_with('+') || rate.starts_with('-') {
+                    1.0 + percent / 100.0
+                } else {
+                    percent / 100.0
+                }
+            } else {
+                rate.parse()
+                    .map_err(|_| format!("Invalid prosody rate: {}", rate))?
+            }
+        }
+    };
+
+    let speed = (current as f64 * factor).round() as i32;
+    Ok(speed.clamp(MIN_SPEED, MAX_SPEED))
+}
+
+/// Maps an SSML pitch onto VOICEPEAK pitch (-300 - 300), relative to `current`.
+/// Semitones map to 100 units each.
+fn prosody_pitch(pitch: &str, current: i32) -> Result<i32, Box<dyn std::error::Error>> {
+    let invalid = || format!("Invalid prosody pitch: {}", pitch);
+
+    let value = match pitch {
+        "x-low" => MIN_PITCH,
+        "low" => MIN_PITCH / 2,
+        "medium" | "default" => 0,
+        "high" => MAX_PITCH / 2,
+        "x-high" => MAX_PITCH,
+        _ => {
+            let relative = pitch.starts_with('+') || pitch.starts_with('-');
+            let offset = if let Some(semitones) = pitch.strip_suffix("st") {
+                semitones.parse::<f64>().map_err(|_| invalid())? * 100.0
+            } else if let Some(percent) = pitch.strip_suffix('%') {
+                let ratio = 1.0 + percent.parse::<f64>().map_err(|_| invalid())? / 100.0;
+                if ratio <= 0.0 {
+                    return Err(invalid().into());
+                }
+                1200.0 * ratio.log2()
+            } else {
+                pitch.parse::<f64>().map_err(|_| invalid())?
+            };
+
+            if relative || pitch.ends_with('%') {
+                current + offset.round() as i32
+            } else {
+                offset.round() as i32
+            }
+        }
+    };
+
+    Ok(value.clamp(MIN_PITCH, MAX_PITCH))
+}
+
+enum Node {
+    Text(String),
+    Start {
+        name: String,
+        attrs: HashMap<String, String>,
+    },
+    Empty {
+        name: String,
+        attrs: HashMap<String, String>,
+    },
+    End {
+        name: String,
+    },
+}
+
+fn tokenize(input: &str) -> Result<Vec<Node>, Box<dyn std::error::Error>> {
+    let mut nodes = Vec::new();
+    let mut rest = input;
+
+    while !rest.is_empty() {
+        let Some(tag_start) = rest.find('<') else {
+            nodes.push(Node::Text(decode_entities(rest)));
+            break;
+        };
+        if tag_start > 0 {
+            nodes.push(Node::Text(decode_entities(&rest[..tag_start])));
+        }
+        rest = &rest[tag_start..];
+
+        // Skip comments, processing instructions and doctype declarations
+        if let Some(after) = rest.strip_prefix("<!--") {
+            let end = after.find("-->").ok_or("Unterminated SSML comment")?;
+            rest = &after[end + 3..];
+            continue;
+        }
+        if rest.starts_with("<?") || rest.starts_with("<!") {
+            let end = rest.find('>').ok_or("Unterminated SSML declaration")?;
+            rest = &rest[end + 1..];
+            continue;
+        }
+
+        let end = find_tag_end(rest).ok_or("Unterminated SSML tag")?;
+        let tag = &rest[1..end];
+        rest = &rest[end + 1..];
+
+        if let Some(name) = tag.strip_prefix('/') {
+            nodes.push(Node::End {
+                name: name.trim().to_string(),
+            });
+        } else if let Some(body) = tag.strip_suffix('/') {
+            let (name, attrs) = parse_tag(body)?;
+            nodes.push(Node::Empty { name, attrs });
+        } else {
+            let (name, attrs) = parse_tag(tag)?;
+            nodes.push(Node::Start { name, attrs });
+        }
+    }
+
+    Ok(nodes)
+}
+
+fn parse_tag(tag: &str) -> Result<(String, HashMap<String, String>), Box<dyn std::error::Error>> {
+    let tag = tag.trim();
+    let name_end = tag.find(char::is_whitespace).unwrap_or(tag.len());
+    let name = tag[..name_end].to_string();
+    if name.is_empty() {
+        return Err(format!("Invalid SSML tag: <{}>", tag).into());
+    }
+
+    let mut attrs = HashMap::new();
+    let mut rest = tag[name_end..].trim_start();
+    while !rest.is_empty() {
+        let (key, after_key) = rest
+            .split_once('=')
+            .ok_or_else(|| format!("Invalid attribute in SSML tag: <{}>", tag))?;
+        let after_key = after_key.trim_start();
+        let quote = after_key
+            .chars()
+            .next()
+            .filter(|c| *c == '"' || *c == '\'')
+            .ok_or_else(|| format!("Attribute values must be quoted: <{}>", tag))?;
+        let value_end = after_key[1..]
+            .find(quote)
+            .ok_or_else(|| format!("Unterminated attribute value: <{}>", tag))?;
+        attrs.insert(
+            key.trim().to_string(),
+            decode_entities(&after_key[1..1 + value_end]),
+        );
+        rest = after_key[value_end + 2..].trim_start();
+    }
+
+    Ok((name, attrs))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn base_voice() -> VoiceSettings {
+        VoiceSettings {
+            narrator: "base".to_string(),
+            emotion: "happy=10".to_string(),
+            ..VoiceSettings::default()
+        }
+    }
+
+    fn parse(text: &str) -> Result<Vec<Segment>, Box<dyn std::error::Error>> {
+        let sad = VoiceSettings {
+            narrator: "夏色花梨".to_string(),
+            emotion: "sad=50".to_string(),
+            ..VoiceSettings::default()
+        };
+        let preset = VoicePreset::from_voice_settings("karin-sad", &sad).unwrap();
+        let presets = HashMap::from([(preset.name.clone(), preset)]);
+        parse_ssml(text, &base_voice(), &presets)
+    }
+
+    fn texts(segments: &[Segment]) -> Vec<&str> {
+        segments.iter().map(|s| s.text.as_str()).collect()
+    }
+
+    #[test]
+    fn detects_ssml() {
+        assert!(looks_like_ssml(
+            "<?xml version=\"1.0\"?>\n<speak>hi</speak>"
+        ));
+        assert!(looks_like_ssml("  <speak>hi</speak>"));
+        assert!(!looks_like_ssml("<p>hi</p>"));
+    }
+
+    #[test]
+    fn breaks_insert_pauses() {
+        let segments =
+            parse(r#"<speak>a<break time="300ms"/>b<break strength="strong"/>c<break/>d</speak>"#)
+                .unwrap();
+        assert_eq!(texts(&segments), ["a", "b", "c", "d"]);
+        let pauses: Vec<Option<Duration>> = segments.iter().map(|s| s.pause_before).collect();
+        assert_eq!(
+            pauses,
+            [
+                None,
+                Some(Duration::from_millis(300)),
+                Some(Duration::from_millis(1000)),
+                Some(Duration::from_millis(500)),
+            ]
+        );
+        assert!(parse(r#"<speak>a<break time="abc"/></speak>"#).is_err());
+        assert!(parse(r#"<speak>a<break strength="huge"/></speak>"#).is_err());
+    }
+
+    #[test]
+    fn paragraphs_pause_longer_than_sentences() {
+        let segments = parse("<speak><p><s>一。</s><s>二。</s></p><p>三。</p></speak>").unwrap();
+        assert_eq!(texts(&segments), ["一。", "二。", "三。"]);
+        assert_eq!(segments[1].pause_before, Some(SENTENCE_PAUSE));
+        assert_eq!(segments[2].pause_before, Some(PARAGRAPH_PAUSE));
+    }
+
+    #[test]
+    fn prosody_changes_speed_and_pitch_inside_the_element() {
+        let segments =
+            parse(r#"<speak>前<prosody rate="+20%" pitch="+2st">中</prosody>後</speak>"#).unwrap();
+        assert_eq!(texts(&segments), ["前", "中", "後"]);
+        assert_eq!(segments[1].voice.speed.as_deref(), Some("120"));
+        assert_eq!(segments[1].voice.pitch.as_deref(), Some("200"));
+        assert_eq!(segments[2].voice.speed, None);
+        assert_eq!(segments[2].voice.pitch, None);
+    }
+
+    #[test]
+    fn prosody_values_are_clamped_or_ignored() {
+        assert_eq!(prosody_rate("x-fast", 100).unwrap(), 175);
+        assert_eq!(prosody_rate("300%", 100).unwrap(), MAX_SPEED);
+        assert_eq!(prosody_pitch("x-low", 0).unwrap(), MIN_PITCH);
+        assert!(prosody_rate("quick", 100).is_err());
+
+        let segments = parse(r#"<speak><prosody pitch="+10Hz">高く</prosody></speak>"#).unwrap();
+        assert_eq!(segments[0].voice.pitch, None);
+    }
+
+    #[test]
+    fn voice_selects_a_preset_or_a_narrator() {
+        let segments = parse(
+            r#"<speak><voice name="karin-sad">悲しい</voice><voice name="Other">別</voice>元</speak>"#,
+        )
+        .unwrap();
+        assert_eq!(segments[0].voice.narrator, "夏色花梨");
+        assert_eq!(segments[0].voice.emotion, "sad=50");
+        // An unknown name keeps the enclosing emotion
+        assert_eq!(segments[1].voice.narrator, "Other");
+        assert_eq!(segments[1].voice.emotion, "happy=10");
+        assert_eq!(segments[2].voice.narrator, "base");
+    }
+
+    #[test]
+    fn reads_say_as_sub_and_entities() {
+        let segments = parse(
+            r#"<speak><say-as interpret-as="characters">ABC</say-as> <sub alias="ダブリュー">W</sub> &lt;&amp;&gt;</speak>"#,
+        )
+        .unwrap();
+        assert_eq!(texts(&segments), ["A B Cダブリュー<&>"]);
+    }
+
+    #[test]
+    fn joins_lines_without_spaces_in_japanese() {
+        let segments = parse("<speak>こんにちは\n  世界。Hello\n world</speak>").unwrap();
+        assert_eq!(texts(&segments), ["こんにちは世界。Hello world"]);
+    }
+
+    #[test]
+    fn rejects_malformed_documents() {
+        for text in [
+            "<speak><p></speak>",
+            "<speak>open",
+            "<speak></p></speak>",
+            "<speak><voice name=unquoted>x</voice></speak>",
+            "<speak><!-- unterminated</speak>",
+        ] {
+            assert!(parse(text).is_err(), "{} was accepted", text);
+        }
+    }
+}
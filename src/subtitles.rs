@@ -0,0 +1,162 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+use crate::text_splitter::TextSplitter;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SubtitleFormat {
+    Srt,
+    WebVtt,
+}
+
+impl SubtitleFormat {
+    pub fn from_path(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        match path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(|ext| ext.to_ascii_lowercase())
+            .as_deref()
+        {
+            Some("srt") => Ok(SubtitleFormat::Srt),
+            Some("vtt") => Ok(SubtitleFormat::WebVtt),
+            _ => Err(format!(
+                "Unsupported subtitle format: {} (use .srt or .vtt)",
+                path.display()
+            )
+            .into()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SubtitleUnit {
+    Chunk,
+    Sentence,
+}
+
+#[derive(Debug, Clone)]
+pub struct SubtitleCue {
+    pub start: Duration,
+    pub end: Duration,
+    pub text: String,
+}
+
+/// Builds cues from chunk texts and their audio durations, accounting for the
+/// silence inserted before each chunk when they are merged.
+pub fn build_cues(
+    chunks: &[String],
+    durations: &[Duration],
+    gaps: &[Duration],
+    unit: SubtitleUnit,
+    splitter: &TextSplitter,
+) -> Vec<SubtitleCue> {
+    let mut cues = Vec::new();
+    let mut offset = Duration::ZERO;
+
+    for ((chunk, duration), gap) in chunks.iter().zip(durations).zip(gaps) {
+        offset += *gap;
+
+        match unit {
+            SubtitleUnit::Chunk => cues.push(SubtitleCue {
+                start: offset,
+                end: offset + *duration,
+                text: chunk.trim().to_string(),
+            }),
+            SubtitleUnit::Sentence => {
+                cues.extend(split_cue_by_sentence(chunk, offset, *duration, splitter));
+            }
+        }
+
+        offset += *duration;
+    }
+
+    cues
+}
+
+// Sentence timings within a chunk are not reported by VOICEPEAK, so the chunk
+// duration is apportioned by character count.
+fn split_cue_by_sentence(
+    chunk: &str,
+    start: Duration,
+    duration: Duration,
+    splitter: &TextSplitter,
+) -> Vec<SubtitleCue> {
+    let sentences: Vec<String> = splitter
+        .split_into_sentences(chunk)
+        .into_iter()
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .collect();
+    let total_chars: usize = sentences.iter().map(|s| s.chars().count()).sum();
+
+    if total_chars == 0 {
+        return Vec::new();
+    }
+
+    let mut cues = Vec::new();
+    let mut consumed = 0;
+
+    for sentence in sentences {
+        let cue_start = start + duration.mul_f64(consumed as f64 / total_chars as f64);
+        consumed += sentence.chars().count();
+        let cue_end = start + duration.mul_f64(consumed as f64 / total_chars as f64);
+        cues.push(SubtitleCue {
+            start: cue_start,
+            end: cue_end,
+            text: sentence,
+        });
+    }
+
+    cues
+}
+
+pub fn format_subtitles(cues: &[SubtitleCue], format: SubtitleFormat) -> String {
+    let mut content = String::new();
+
+    if format == SubtitleFormat::WebVtt {
+        content.push_str("WEBVTT\n\n");
+    }
+
+    for (i, cue) in cues.iter().enumerate() {
+        if format == SubtitleFormat::Srt {
+            content.push_str(&format!("{}\n", i + 1));
+        }
+        content.push_str(&format!(
+            "{} --> {}\n{}\n\n",
+            format_timestamp(cue.start, format),
+            format_timestamp(cue.end, format),
+            cue.text
+        ));
+    }
+
+    content
+}
+
+pub fn write_subtitles(
+    path: &Path,
+    cues: &[SubtitleCue],
+) -> Result<(), Box<dyn std::error::Error>> {
+    let format = SubtitleFormat::from_path(path)?;
+    fs::write(path, format_subtitles(cues, format))?;
+    Ok(())
+}
+
+fn format_timestamp(time: Duration, format: SubtitleFormat) -> String {
+    let total_millis = time.as_millis();
+    let hours = total_millis / 3_600_000;
+    let minutes = (total_millis / 60_000) % 60;
+    let seconds = (total_millis / 1000) % 60;
+    let millis = total_millis % 1000;
+    let separator = match format {
+        SubtitleFormat::Srt => ',',
+        SubtitleFormat::WebVtt => '.',
+    };
+
+    format!(
+        "{:02}:{:02}:{:02}{}{:03}",
+        hours, minutes, seconds, separator, millis
+    )
+}
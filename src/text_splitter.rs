@@ -1,116 +1,380 @@
+use serde::{Deserialize, Serialize};
+
 pub const MAX_CHARS: usize = 140;
+pub const SENTENCE_TERMINATORS: &str = "。！？.!?";
+pub const SOFT_BREAKS: &str = "、，, 　";
+pub const ABBREVIATIONS: &[&str] = &[
+    "e.g.", "i.e.", "Mr.", "Mrs.", "Ms.", "Dr.", "Prof.", "St.", "vs.", "No.", "Jr.", "Sr.",
+    "Inc.", "Ltd.", "Co.",
+];
 
-pub fn check_text_length(text: &str) -> bool {
-    text.chars().count() <= MAX_CHARS
+const OPENING_BRACKETS: &str = "「『（(【〔［[〈《“";
+const CLOSING_BRACKETS: &str = "」』）)】〕］]〉》”";
+// Characters kept with the sentence they follow, e.g. `。」` or `." `
+const TRAILING_CLOSERS: &str = "」』）)】〕］]〉》”’\"'";
+
+/// Splitting options from the `[splitter]` section of the config file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SplitterConfig {
+    pub max_chars: usize,
+    pub sentence_terminators: String,
+    pub soft_breaks: String,
+    /// Words ending in `.` that do not end a sentence.
+    pub abbreviations: Vec<String>,
 }
 
-pub fn split_text(text: &str) -> Vec<String> {
-    if text.chars().count() <= MAX_CHARS {
-        return vec![text.to_string()];
+impl Default for SplitterConfig {
+    fn default() -> Self {
+        Self {
+            max_chars: MAX_CHARS,
+            sentence_terminators: SENTENCE_TERMINATORS.to_string(),
+            soft_breaks: SOFT_BREAKS.to_string(),
+            abbreviations: ABBREVIATIONS.iter().map(|a| a.to_string()).collect(),
+        }
     }
+}
 
-    let mut chunks = Vec::new();
-    let mut current_chunk = String::new();
-    let mut chars_count = 0;
+/// Splits text into chunks VOICEPEAK can synthesize in one call.
+///
+/// Text is cut at sentence terminators first, then long sentences are cut at
+/// the last soft break before `max_chars`, and finally hard-cut. Blank lines
+/// end a paragraph, and a chunk never spans two paragraphs.
+///
+/// A terminator does not end a sentence inside brackets or quotes, after an
+/// abbreviation, or (for ASCII terminators) when not followed by whitespace,
+/// which keeps `3.14` and `example.com` intact. Closing brackets and quotes
+/// stay with the sentence they end.
+#[derive(Debug, Clone)]
+pub struct TextSplitter {
+    max_chars: usize,
+    sentence_terminators: Vec<char>,
+    soft_breaks: Vec<char>,
+    abbreviations: Vec<String>,
+}
 
-    let sentences = split_into_sentences(text);
+/// Why a chunk ends where it does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Boundary {
+    /// The next sentence would not fit.
+    Sentence,
+    /// A blank line follows.
+    Paragraph,
+    /// A long sentence was cut at a soft break character.
+    SoftBreak,
+    /// A long sentence had no soft break and was cut at the length limit.
+    HardCut,
+    /// End of the text (or of a segment, for markup and other input formats).
+    End,
+}
 
-    for sentence in sentences {
-        let sentence_len = sentence.chars().count();
+impl Boundary {
+    pub fn description(&self) -> &'static str {
+        match self {
+            Boundary::Sentence => "sentence end",
+            Boundary::Paragraph => "paragraph end",
+            Boundary::SoftBreak => "soft break",
+            Boundary::HardCut => "hard cut",
+            Boundary::End => "end of text",
+        }
+    }
+}
 
-        if chars_count + sentence_len <= MAX_CHARS {
-            current_chunk.push_str(&sentence);
-            chars_count += sentence_len;
-        } else {
-            if !current_chunk.is_empty() {
-                chunks.push(current_chunk.trim().to_string());
-                current_chunk = String::new();
-                chars_count = 0;
-            }
+struct Sentence {
+    text: String,
+    ends_paragraph: bool,
+}
+
+impl Default for TextSplitter {
+    fn default() -> Self {
+        Self::from_config(&SplitterConfig::default())
+    }
+}
+
+impl TextSplitter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn from_config(config: &SplitterConfig) -> Self {
+        Self {
+            max_chars: MAX_CHARS,
+            sentence_terminators: Vec::new(),
+            soft_breaks: Vec::new(),
+            abbreviations: Vec::new(),
+        }
+        .max_chars(config.max_chars)
+        .sentence_terminators(&config.sentence_terminators)
+        .soft_breaks(&config.soft_breaks)
+        .abbreviations(&config.abbreviations)
+    }
+
+    pub fn max_chars(mut self, max_chars: usize) -> Self {
+        // A zero limit would never make progress
+        self.max_chars = max_chars.max(1);
+        self
+    }
+
+    pub fn sentence_terminators(mut self, terminators: &str) -> Self {
+        self.sentence_terminators = terminators.chars().collect();
+        self
+    }
+
+    pub fn soft_breaks(mut self, breaks: &str) -> Self {
+        self.soft_breaks = breaks.chars().collect();
+        self
+    }
+
+    pub fn abbreviations(mut self, abbreviations: &[String]) -> Self {
+        self.abbreviations = abbreviations.to_vec();
+        self
+    }
+
+    pub fn get_max_chars(&self) -> usize {
+        self.max_chars
+    }
+
+    pub fn check_length(&self, text: &str) -> bool {
+        text.chars().count() <= self.max_chars
+    }
 
-            if sentence_len <= MAX_CHARS {
+    pub fn split(&self, text: &str) -> Vec<String> {
+        self.split_with_boundaries(text)
+            .into_iter()
+            .map(|(chunk, _)| chunk)
+            .collect()
+    }
+
+    /// Splits `text` like [`split`](Self::split), also reporting why each
+    /// chunk ends where it does.
+    pub fn split_with_boundaries(&self, text: &str) -> Vec<(String, Boundary)> {
+        if self.check_length(text) && !has_blank_line(text) {
+            return vec![(text.to_string(), Boundary::End)];
+        }
+
+        let mut chunks = Vec::new();
+        let mut current_chunk = String::new();
+        let mut chars_count = 0;
+
+        for Sentence {
+            text: sentence,
+            ends_paragraph,
+        } in self.sentences(text)
+        {
+            let sentence_len = sentence.chars().count();
+
+            if chars_count + sentence_len <= self.max_chars {
                 current_chunk.push_str(&sentence);
-                chars_count = sentence_len;
+                chars_count += sentence_len;
             } else {
-                let sub_chunks = split_long_sentence(&sentence);
-                for (i, sub_chunk) in sub_chunks.iter().enumerate() {
-                    if i == sub_chunks.len() - 1 {
-                        current_chunk.push_str(sub_chunk);
-                        chars_count = sub_chunk.chars().count();
-                    } else {
-                        chunks.push(sub_chunk.trim().to_string());
+                if !current_chunk.is_empty() {
+                    chunks.push((current_chunk.trim().to_string(), Boundary::Sentence));
+                    current_chunk = String::new();
+                    chars_count = 0;
+                }
+
+                if sentence_len <= self.max_chars {
+                    current_chunk.push_str(&sentence);
+                    chars_count = sentence_len;
+                } else {
+                    let mut sub_chunks = self.split_long_sentence(&sentence);
+                    if let Some((last, _)) = sub_chunks.pop() {
+                        chunks.extend(
+                            sub_chunks.into_iter().map(|(sub_chunk, boundary)| {
+                                (sub_chunk.trim().to_string(), boundary)
+                            }),
+                        );
+                        chars_count = last.chars().count();
+                        current_chunk = last;
                     }
                 }
             }
+
+            if ends_paragraph && !current_chunk.trim().is_empty() {
+                chunks.push((current_chunk.trim().to_string(), Boundary::Paragraph));
+                current_chunk.clear();
+                chars_count = 0;
+            }
+        }
+
+        if !current_chunk.trim().is_empty() {
+            chunks.push((current_chunk.trim().to_string(), Boundary::End));
         }
+
+        chunks
     }
 
-    if !current_chunk.trim().is_empty() {
-        chunks.push(current_chunk.trim().to_string());
+    pub fn split_into_sentences(&self, text: &str) -> Vec<String> {
+        self.sentences(text).into_iter().map(|s| s.text).collect()
     }
 
-    chunks
-}
+    fn sentences(&self, text: &str) -> Vec<Sentence> {
+        let chars: Vec<char> = text.chars().collect();
+        let mut sentences = Vec::new();
+        let mut current_sentence = String::new();
+        let mut depth = 0usize;
+        let mut i = 0;
 
-fn split_into_sentences(text: &str) -> Vec<String> {
-    let sentence_endings = ['。', '！', '？', '.', '!', '?'];
-    let mut sentences = Vec::new();
-    let mut current_sentence = String::new();
+        while i < chars.len() {
+            let ch = chars[i];
 
-    for ch in text.chars() {
-        current_sentence.push(ch);
+            // A run of whitespace containing a blank line ends the paragraph
+            if ch == '\n' {
+                let run_end = chars[i..]
+                    .iter()
+                    .position(|c| !c.is_whitespace())
+                    .map_or(chars.len(), |n| i + n);
+                if chars[i..run_end].iter().filter(|c| **c == '\n').count() >= 2 {
+                    current_sentence.extend(&chars[i..run_end]);
+                    sentences.push(Sentence {
+                        text: std::mem::take(&mut current_sentence),
+                        ends_paragraph: true,
+                    });
+                    depth = 0;
+                    i = run_end;
+                    continue;
+                }
+            }
+
+            current_sentence.push(ch);
+            i += 1;
+
+            if OPENING_BRACKETS.contains(ch) {
+                depth += 1;
+            } else if CLOSING_BRACKETS.contains(ch) {
+                depth = depth.saturating_sub(1);
+            }
+
+            // Unbalanced brackets must not stop splitting altogether
+            let quoted = depth > 0 && current_sentence.chars().count() <= self.max_chars;
+            let ends_sentence = self.sentence_terminators.contains(&ch)
+                && !quoted
+                && self.is_sentence_end(&chars, i - 1, &current_sentence);
+            if !ends_sentence {
+                continue;
+            }
+
+            while i < chars.len()
+                && (self.sentence_terminators.contains(&chars[i])
+                    || TRAILING_CLOSERS.contains(chars[i]))
+            {
+                if CLOSING_BRACKETS.contains(chars[i]) {
+                    depth = depth.saturating_sub(1);
+                }
+                current_sentence.push(chars[i]);
+                i += 1;
+            }
+            sentences.push(Sentence {
+                text: std::mem::take(&mut current_sentence),
+                ends_paragraph: false,
+            });
+        }
 
-        if sentence_endings.contains(&ch) {
-            sentences.push(current_sentence.clone());
-            current_sentence.clear();
+        if !current_sentence.trim().is_empty() {
+            sentences.push(Sentence {
+                text: current_sentence,
+                ends_paragraph: false,
+            });
         }
+
+        sentences
     }
 
-    if !current_sentence.trim().is_empty() {
-        sentences.push(current_sentence);
+    // `chars[index]` is a sentence terminator and `sentence` ends with it
+    fn is_sentence_end(&self, chars: &[char], index: usize, sentence: &str) -> bool {
+        let ch = chars[index];
+        if !ch.is_ascii() {
+            // Runs like `？！` are absorbed after the first one
+            return true;
+        }
+
+        // ASCII terminators need a following space, so decimals (3.14), URLs
+        // and ellipses ("...") are not split
+        match chars.get(index + 1) {
+            Some(c) if self.sentence_terminators.contains(c) => return false,
+            Some(c) if c.is_whitespace() || TRAILING_CLOSERS.contains(*c) => {}
+            Some(c) if c.is_ascii() => return false,
+            _ => {}
+        }
+
+        ch != '.' || !self.ends_with_abbreviation(sentence)
     }
 
-    sentences
-}
+    fn ends_with_abbreviation(&self, sentence: &str) -> bool {
+        self.abbreviations.iter().any(|abbreviation| {
+            let Some(head) = sentence
+                .len()
+                .checked_sub(abbreviation.len())
+                .and_then(|start| sentence.get(start..).map(|tail| (start, tail)))
+                .filter(|(_, tail)| tail == abbreviation)
+                .map(|(start, _)| &sentence[..start])
+            else {
+                return false;
+            };
+            // Whole words only: "Dr." but not "Mr. Smithdr."
+            !head.chars().last().is_some_and(|c| c.is_alphanumeric())
+        })
+    }
 
-fn split_long_sentence(sentence: &str) -> Vec<String> {
-    let break_points = ['、', '，', ',', ' ', '　'];
-    let mut chunks = Vec::new();
-    let mut current_chunk = String::new();
-    let mut chars_count = 0;
+    // The last piece is the remainder of the sentence, which callers
+    // continue the next chunk with
+    fn split_long_sentence(&self, sentence: &str) -> Vec<(String, Boundary)> {
+        let mut chunks = Vec::new();
+        let mut current_chunk = String::new();
+        let mut chars_count = 0;
 
-    for ch in sentence.chars() {
-        current_chunk.push(ch);
-        chars_count += 1;
+        for ch in sentence.chars() {
+            current_chunk.push(ch);
+            chars_count += 1;
 
-        if chars_count >= MAX_CHARS {
-            if break_points.contains(&ch) {
-                chunks.push(current_chunk.clone());
-                current_chunk.clear();
-                chars_count = 0;
-            } else {
-                let last_break = find_last_break_point(&current_chunk, &break_points);
-                if let Some(char_pos) = last_break {
-                    let chars: Vec<char> = current_chunk.chars().collect();
-                    let first_part: String = chars[..=char_pos].iter().collect();
-                    let second_part: String = chars[char_pos + 1..].iter().collect();
-                    chunks.push(first_part);
-                    current_chunk = second_part;
-                    chars_count = current_chunk.chars().count();
-                } else {
-                    chunks.push(current_chunk.clone());
-                    current_chunk.clear();
+            if chars_count >= self.max_chars {
+                if self.soft_breaks.contains(&ch) {
+                    chunks.push((std::mem::take(&mut current_chunk), Boundary::SoftBreak));
                     chars_count = 0;
+                } else {
+                    // Terminators inside quotes are not sentence ends, but
+                    // are still better places to cut than an arbitrary one
+                    let break_points: Vec<char> = self
+                        .soft_breaks
+                        .iter()
+                        .chain(&self.sentence_terminators)
+                        .copied()
+                        .collect();
+                    let last_break = find_last_break_point(&current_chunk, &break_points);
+                    if let Some(char_pos) = last_break {
+                        let chars: Vec<char> = current_chunk.chars().collect();
+                        let first_part: String = chars[..=char_pos].iter().collect();
+                        let second_part: String = chars[char_pos + 1..].iter().collect();
+                        chunks.push((first_part, Boundary::SoftBreak));
+                        current_chunk = second_part;
+                        chars_count = current_chunk.chars().count();
+                    } else {
+                        chunks.push((std::mem::take(&mut current_chunk), Boundary::HardCut));
+                        chars_count = 0;
+                    }
                 }
             }
         }
-    }
 
-    if !current_chunk.trim().is_empty() {
-        chunks.push(current_chunk);
+        if !current_chunk.trim().is_empty() {
+            chunks.push((current_chunk, Boundary::Sentence));
+        }
+
+        chunks
     }
+}
+
+pub fn check_text_length(text: &str) -> bool {
+    TextSplitter::default().check_length(text)
+}
+
+pub fn split_text(text: &str) -> Vec<String> {
+    TextSplitter::default().split(text)
+}
 
-    chunks
+pub fn split_into_sentences(text: &str) -> Vec<String> {
+    TextSplitter::default().split_into_sentences(text)
 }
 
 fn find_last_break_point(text: &str, break_points: &[char]) -> Option<usize> {
@@ -122,3 +386,92 @@ fn find_last_break_point(text: &str, break_points: &[char]) -> Option<usize> {
         .find(|(_, ch)| break_points.contains(ch))
         .map(|(i, _)| i)
 }
+
+fn has_blank_line(text: &str) -> bool {
+    text.trim().lines().any(|line| line.trim().is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sentences(text: &str) -> Vec<String> {
+        split_into_sentences(text)
+            .into_iter()
+            .map(|s| s.trim().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn keeps_decimals_in_one_sentence() {
+        assert_eq!(
+            sentences("円周率は3.14です。次の文です。"),
+            ["円周率は3.14です。", "次の文です。"]
+        );
+    }
+
+    #[test]
+    fn does_not_split_after_abbreviations() {
+        assert_eq!(
+            sentences("Use a tool, e.g. vp. It works."),
+            ["Use a tool, e.g. vp.", "It works."]
+        );
+    }
+
+    #[test]
+    fn does_not_split_inside_quotes() {
+        assert_eq!(
+            sentences("彼は「はい。そうです。」と言った。次です。"),
+            ["彼は「はい。そうです。」と言った。", "次です。"]
+        );
+    }
+
+    #[test]
+    fn blank_line_ends_a_chunk() {
+        assert_eq!(
+            TextSplitter::default().split_with_boundaries("一段落目。\n\n二段落目。"),
+            [
+                ("一段落目。".to_string(), Boundary::Paragraph),
+                ("二段落目。".to_string(), Boundary::End),
+            ]
+        );
+    }
+
+    #[test]
+    fn long_sentences_are_cut_at_soft_breaks_then_hard_cut() {
+        let splitter = TextSplitter::new().max_chars(8);
+        assert_eq!(
+            splitter.split_with_boundaries("あいうえお、かきくけこさしすせ。"),
+            [
+                ("あいうえお、".to_string(), Boundary::SoftBreak),
+                ("かきくけこさしす".to_string(), Boundary::HardCut),
+                ("せ。".to_string(), Boundary::End),
+            ]
+        );
+    }
+
+    #[test]
+    fn uses_configured_characters_and_abbreviations() {
+        let config = SplitterConfig {
+            max_chars: 0,
+            sentence_terminators: "。;.".to_string(),
+            soft_breaks: "・".to_string(),
+            abbreviations: vec!["approx.".to_string()],
+        };
+        let splitter = TextSplitter::from_config(&config);
+        assert_eq!(splitter.get_max_chars(), 1);
+
+        // The configured list replaces the default abbreviations, and `！` is
+        // no longer a terminator
+        let splitter = splitter.max_chars(MAX_CHARS);
+        let sentences: Vec<String> = splitter
+            .split_into_sentences("one; approx. 3 items. Dr. Who。四！五")
+            .into_iter()
+            .map(|s| s.trim().to_string())
+            .collect();
+        assert_eq!(
+            sentences,
+            ["one;", "approx. 3 items.", "Dr.", "Who。", "四！五"]
+        );
+    }
+}
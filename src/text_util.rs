@@ -0,0 +1,150 @@
+// Wrapped lines are joined with a space only between ASCII text, so Japanese
+// sentences broken across lines are not read with an extra pause.
+pub(crate) fn join_lines(lines: &[String]) -> String {
+    let mut joined = String::new();
+
+    for line in lines.iter().map(|l| l.trim()).filter(|l| !l.is_empty()) {
+        if let (Some(prev), Some(next)) = (joined.chars().last(), line.chars().next()) {
+            if prev.is_ascii() && next.is_ascii() {
+                joined.push(' ');
+            }
+        }
+        joined.push_str(line);
+    }
+
+    joined
+}
+
+// List items, table rows and headings usually lack closing punctuation. The
+// added terminator matches the script the text ends in.
+pub(crate) fn as_sentence(text: &str) -> String {
+    let text = text.trim();
+    match text.chars().last() {
+        None => String::new(),
+        Some(last) if "。．.！!？?：:".contains(last) => text.to_string(),
+        Some(last) if is_cjk(last) => format!("{}。", text),
+        Some(_) => format!("{}.", text),
+    }
+}
+
+pub(crate) fn is_cjk(ch: char) -> bool {
+    matches!(ch as u32,
+        0x3000..=0x30FF // CJK punctuation, hiragana and katakana
+        | 0x31F0..=0x31FF // katakana extensions
+        | 0x3400..=0x4DBF // CJK extension A
+        | 0x4E00..=0x9FFF // CJK unified ideographs
+        | 0xF900..=0xFAFF // CJK compatibility ideographs
+        | 0xFF00..=0xFFEF // full-width forms
+    )
+}
+
+// Finds the closing '>' of a tag, ignoring any inside quoted attribute values
+pub(crate) fn find_tag_end(tag: &str) -> Option<usize> {
+    let mut quote: Option<char> = None;
+    for (i, ch) in tag.char_indices() {
+        match (quote, ch) {
+            (None, '"' | '\'') => quote = Some(ch),
+            (Some(q), c) if q == c => quote = None,
+            (None, '>') => return Some(i),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Decodes XML entities, common HTML named entities and numeric character
+/// references. Unknown entities are kept as written.
+pub(crate) fn decode_entities(text: &str) -> String {
+    let mut decoded = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(amp) = rest.find('&') {
+        decoded.push_str(&rest[..amp]);
+        rest = &rest[amp..];
+
+        let Some(semi) = rest.find(';') else {
+            break;
+        };
+        let entity = &rest[1..semi];
+        let replacement = match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            // Common HTML entities, for extracting text from web pages
+            "nbsp" => Some(' '),
+            "ndash" => Some('–'),
+            "mdash" => Some('—'),
+            "hellip" => Some('…'),
+            "middot" => Some('·'),
+            "lsquo" => Some('‘'),
+            "rsquo" => Some('’'),
+            "ldquo" => Some('“'),
+            "rdquo" => Some('”'),
+            "laquo" => Some('«'),
+            "raquo" => Some('»'),
+            "copy" => Some('©'),
+            "yen" => Some('¥'),
+            _ => entity
+                .strip_prefix("#x")
+                .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                .or_else(|| entity.strip_prefix('#').and_then(|dec| dec.parse().ok()))
+                .and_then(char::from_u32),
+        };
+
+        match replacement {
+            Some(ch) => {
+                decoded.push(ch);
+                rest = &rest[semi + 1..];
+            }
+            None => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+
+    decoded.push_str(rest);
+    decoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ends_sentences_in_the_script_of_the_text() {
+        assert_eq!(as_sentence("見出し"), "見出し。");
+        assert_eq!(as_sentence("Hello world"), "Hello world.");
+        assert_eq!(as_sentence("Version 2"), "Version 2.");
+        assert_eq!(as_sentence(" 終わり。 "), "終わり。");
+        assert_eq!(as_sentence("Really?"), "Really?");
+        assert_eq!(as_sentence(""), "");
+    }
+
+    #[test]
+    fn joins_lines_with_spaces_only_between_ascii() {
+        let lines = |l: &[&str]| l.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        assert_eq!(join_lines(&lines(&["Hello", " world "])), "Hello world");
+        assert_eq!(
+            join_lines(&lines(&["日本語の", "", "文章"])),
+            "日本語の文章"
+        );
+        assert_eq!(join_lines(&lines(&["Rust", "は速い"])), "Rustは速い");
+    }
+
+    #[test]
+    fn finds_tag_ends_outside_quotes() {
+        assert_eq!(find_tag_end(r#"<a href="x>y">text"#), Some(13));
+        assert_eq!(find_tag_end("<a title='>'>"), Some(12));
+        assert_eq!(find_tag_end(r#"<a href="x>"#), None);
+    }
+
+    #[test]
+    fn decodes_entities() {
+        assert_eq!(decode_entities("&lt;a&gt; &amp; &quot;"), "<a> & \"");
+        assert_eq!(decode_entities("&#x3042;&#12354;&hellip;"), "ああ…");
+        assert_eq!(decode_entities("&unknown; & AT&T"), "&unknown; & AT&T");
+    }
+}
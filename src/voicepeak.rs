@@ -1,4 +1,5 @@
 use fs2::FileExt;
+use serde::{Deserialize, Serialize};
 use std::fs::{create_dir_all, File, OpenOptions};
 use std::path::PathBuf;
 use std::process::{Command as ProcessCommand, Output, Stdio};
@@ -8,6 +9,39 @@ use std::time::Duration;
 
 const VOICEPEAK_PATH: &str = "/Applications/voicepeak.app/Contents/MacOS/voicepeak";
 
+/// Identifies the installed VOICEPEAK build, so cached audio is not reused
+/// across engine updates. Falls back to the binary's modification time when
+/// the bundle version cannot be read.
+pub fn engine_version() -> String {
+    let info_plist = std::path::Path::new(VOICEPEAK_PATH)
+        .ancestors()
+        .nth(2)
+        .map(|contents| contents.join("Info.plist"));
+    let bundle_version = info_plist
+        .and_then(|path| std::fs::read_to_string(path).ok())
+        .and_then(|plist| {
+            let after_key = plist
+                .split("<key>CFBundleShortVersionString</key>")
+                .nth(1)?;
+            let value = after_key
+                .split("<string>")
+                .nth(1)?
+                .split("</string>")
+                .next()?;
+            Some(value.trim().to_string())
+        });
+
+    bundle_version
+        .or_else(|| {
+            std::fs::metadata(VOICEPEAK_PATH)
+                .and_then(|metadata| metadata.modified())
+                .ok()
+                .and_then(|modified| modified.duration_since(std::time::UNIX_EPOCH).ok())
+                .map(|since_epoch| format!("mtime-{}", since_epoch.as_secs()))
+        })
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
 fn get_lock_file() -> Result<File, Box<dyn std::error::Error>> {
     let lock_path = get_lock_file_path()?;
 
@@ -63,6 +97,37 @@ pub fn list_emotion(narrator: &str) {
     }
 }
 
+/// Installed narrator names, one per line of `--list-narrator`.
+pub fn narrators() -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    list_lines(&["--list-narrator"])
+}
+
+/// Emotion names supported by `narrator`.
+pub fn emotions(narrator: &str) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    list_lines(&["--list-emotion", narrator])
+}
+
+fn list_lines(args: &[&str]) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    // Listing starts VOICEPEAK too, so it must not overlap with synthesis
+    let lock_file = get_lock_file()?;
+    lock_file.lock_exclusive()?;
+
+    let output = ProcessCommand::new(VOICEPEAK_PATH)
+        .args(args)
+        .output()
+        .map_err(|e| format!("Failed to execute voicepeak: {}", e))?;
+    if !output.status.success() {
+        return Err("voicepeak command failed".into());
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(str::to_string)
+        .collect())
+}
+
 #[derive(Debug, Clone)]
 struct CommandArgs {
     text: Option<String>,
@@ -77,6 +142,33 @@ pub struct VoicepeakCommand {
     args: CommandArgs,
 }
 
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct VoiceSettings {
+    pub narrator: String,
+    pub emotion: String,
+    pub speed: Option<String>,
+    pub pitch: Option<String>,
+}
+
+impl VoiceSettings {
+    pub fn command(&self, text: &str, output: &std::path::Path) -> VoicepeakCommand {
+        let mut cmd = VoicepeakCommand::new()
+            .text(text)
+            .narrator(&self.narrator)
+            .emotion(&self.emotion)
+            .output(output);
+
+        if let Some(ref speed) = self.speed {
+            cmd = cmd.speed(speed);
+        }
+        if let Some(ref pitch) = self.pitch {
+            cmd = cmd.pitch(pitch);
+        }
+
+        cmd
+    }
+}
+
 fn execute_command_with_timeout(
     mut command: ProcessCommand,
     timeout_secs: u64,
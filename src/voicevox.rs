@@ -0,0 +1,194 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use tiny_http::{Method, Request, Response};
+
+use crate::config::VoiceOptions;
+use crate::server::{
+    audio_response, json_response, read_json, scale_speed, ApiError, ApiResponse, AudioFormat,
+    ServerState,
+};
+use crate::voicepeak::VoiceSettings;
+
+/// Port VOICEVOX clients connect to by default.
+pub const VOICEVOX_LISTEN_ADDRESS: &str = "127.0.0.1:50021";
+
+const PITCH_RANGE: (i32, i32) = (-300, 300);
+
+#[derive(Serialize)]
+struct Speaker {
+    name: String,
+    speaker_uuid: String,
+    styles: Vec<SpeakerStyle>,
+    version: &'static str,
+}
+
+#[derive(Serialize)]
+struct SpeakerStyle {
+    name: String,
+    id: usize,
+}
+
+/// The VOICEVOX `AudioQuery`. Accent phrases are not modelled; the text to
+/// speak travels in `kana`, which clients send back unchanged.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AudioQuery {
+    #[serde(default)]
+    pub accent_phrases: Vec<serde_json::Value>,
+    #[serde(rename = "speedScale", default = "one")]
+    pub speed_scale: f64,
+    #[serde(rename = "pitchScale", default)]
+    pub pitch_scale: f64,
+    #[serde(rename = "intonationScale", default = "one")]
+    pub intonation_scale: f64,
+    #[serde(rename = "volumeScale", default = "one")]
+    pub volume_scale: f64,
+    #[serde(rename = "prePhonemeLength", default)]
+    pub pre_phoneme_length: f64,
+    #[serde(rename = "postPhonemeLength", default)]
+    pub post_phoneme_length: f64,
+    #[serde(rename = "outputSamplingRate", default = "sampling_rate")]
+    pub output_sampling_rate: u32,
+    #[serde(rename = "outputStereo", default)]
+    pub output_stereo: bool,
+    #[serde(default)]
+    pub kana: Option<String>,
+}
+
+fn one() -> f64 {
+    1.0
+}
+
+fn sampling_rate() -> u32 {
+    48000
+}
+
+impl AudioQuery {
+    fn new(text: &str) -> Self {
+        Self {
+            accent_phrases: Vec::new(),
+            speed_scale: 1.0,
+            pitch_scale: 0.0,
+            intonation_scale: 1.0,
+            volume_scale: 1.0,
+            pre_phoneme_length: 0.1,
+            post_phoneme_length: 0.1,
+            output_sampling_rate: sampling_rate(),
+            output_stereo: false,
+            kana: Some(text.to_string()),
+        }
+    }
+
+    /// Applies the speed and pitch scales on top of the style's voice.
+    /// `speedScale` multiplies VOICEPEAK's speed (100 = normal) and
+    /// `pitchScale`, a shift in log frequency, is converted to cents.
+    pub fn apply_scales(&self, voice: &mut VoiceSettings) {
+        if self.speed_scale != 1.0 {
+            scale_speed(voice, self.speed_scale);
+        }
+        if self.pitch_scale != 0.0 {
+            let cents = (self.pitch_scale * 1200.0 / std::f64::consts::LN_2).round() as i32;
+            let base = voice
+                .pitch
+                .as_deref()
+                .and_then(|v| v.parse::<i32>().ok())
+                .unwrap_or(0);
+            let pitch = base + cents;
+            voice.pitch = Some(pitch.clamp(PITCH_RANGE.0, PITCH_RANGE.1).to_string());
+        }
+    }
+}
+
+/// Deterministic UUID-shaped id for a narrator, so clients can remember
+/// speakers across restarts.
+fn speaker_uuid(narrator: &str) -> String {
+    let hex: String = Sha256::digest(narrator.as_bytes())
+        .iter()
+        .take(16)
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..]
+    )
+}
+
+/// Speakers are narrators and styles are the presets using them. A style id
+/// is the preset's position in the config.
+fn speakers(state: &ServerState) -> Vec<Speaker> {
+    let mut speakers: Vec<Speaker> = Vec::new();
+    for (id, preset) in state.config.presets.iter().enumerate() {
+        let style = SpeakerStyle {
+            name: preset.name.clone(),
+            id,
+        };
+        match speakers.iter_mut().find(|s| s.name == preset.narrator) {
+            Some(speaker) => speaker.styles.push(style),
+            None => speakers.push(Speaker {
+                name: preset.narrator.clone(),
+                speaker_uuid: speaker_uuid(&preset.narrator),
+                styles: vec![style],
+                version: env!("CARGO_PKG_VERSION"),
+            }),
+        }
+    }
+    speakers
+}
+
+fn style_voice(
+    state: &ServerState,
+    query: &HashMap<String, String>,
+) -> Result<VoiceSettings, ApiError> {
+    let id = query
+        .get("speaker")
+        .ok_or_else(|| ApiError::bad_request("Missing query parameter: speaker"))?;
+    let preset = id
+        .parse::<usize>()
+        .ok()
+        .and_then(|id| state.config.presets.get(id))
+        .ok_or_else(|| ApiError::not_found(format!("Unknown style id: {}", id)))?;
+
+    state.resolve_voice(&VoiceOptions {
+        preset: Some(preset.name.clone()),
+        ..VoiceOptions::default()
+    })
+}
+
+/// Handles the VOICEVOX engine routes. Returns `None` for other paths.
+pub fn handle_voicevox(
+    state: &ServerState,
+    request: &mut Request,
+    segments: &[&str],
+    query: &HashMap<String, String>,
+) -> Option<Result<ApiResponse, ApiError>> {
+    let response = match (request.method(), segments) {
+        (Method::Get, ["version"]) => Ok(json_response(200, &env!("CARGO_PKG_VERSION"))),
+        (Method::Get, ["speakers"]) => Ok(json_response(200, &speakers(state))),
+        // Nothing needs loading; VOICEPEAK starts per synthesis
+        (Method::Get, ["is_initialized_speaker"]) => Ok(json_response(200, &true)),
+        (Method::Post, ["initialize_speaker"]) => {
+            Ok(Response::from_data(Vec::new()).with_status_code(204))
+        }
+        (Method::Post, ["audio_query"]) => style_voice(state, query).and_then(|_| {
+            let text = query
+                .get("text")
+                .ok_or_else(|| ApiError::bad_request("Missing query parameter: text"))?;
+            Ok(json_response(200, &AudioQuery::new(text)))
+        }),
+        (Method::Post, ["synthesis"]) => style_voice(state, query).and_then(|mut voice| {
+            let audio_query: AudioQuery = read_json(request)?;
+            let text = audio_query.kana.as_deref().ok_or_else(|| {
+                ApiError::bad_request("AudioQuery has no text; create it with /audio_query")
+            })?;
+            audio_query.apply_scales(&mut voice);
+            let audio = state.synthesize(text, &voice, AudioFormat::Wav)?;
+            Ok(audio_response(audio, AudioFormat::Wav))
+        }),
+        _ => return None,
+    };
+    Some(response)
+}
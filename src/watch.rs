@@ -0,0 +1,142 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use crate::markdown::is_markdown_path;
+
+/// How often watched files are checked for changes.
+pub const WATCH_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Whether a file in a watched directory should be rendered.
+pub fn is_watched_text_path(path: &Path) -> bool {
+    is_markdown_path(path)
+        || path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("txt"))
+}
+
+/// Reads text appended to a file since the last call.
+pub struct FileTail {
+    path: PathBuf,
+    offset: u64,
+}
+
+impl FileTail {
+    /// Starts at the current end of the file, so only new text is returned.
+    pub fn open(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        Ok(Self {
+            path: path.to_path_buf(),
+            offset: fs::metadata(path)?.len(),
+        })
+    }
+
+    /// Returns the text appended since the last call, if any. A file that
+    /// shrank was rewritten, so reading restarts from its new end.
+    pub fn read_appended(&mut self) -> Result<Option<String>, Box<dyn std::error::Error>> {
+        let len = fs::metadata(&self.path)?.len();
+        if len < self.offset {
+            self.offset = len;
+        }
+        if len == self.offset {
+            return Ok(None);
+        }
+
+        let mut file = fs::File::open(&self.path)?;
+        file.seek(SeekFrom::Start(self.offset))?;
+        let mut bytes = Vec::new();
+        file.take(len - self.offset).read_to_end(&mut bytes)?;
+
+        // A character still being written is left for the next call
+        let text = match String::from_utf8(bytes) {
+            Ok(text) => text,
+            Err(e) => {
+                let valid = e.utf8_error().valid_up_to();
+                let mut bytes = e.into_bytes();
+                bytes.truncate(valid);
+                String::from_utf8(bytes)?
+            }
+        };
+        self.offset += text.len() as u64;
+
+        Ok((!text.trim().is_empty()).then_some(text))
+    }
+}
+
+/// Polls a file, or the `.txt` and `.md` files under a directory, for
+/// modifications.
+pub struct FileWatcher {
+    root: PathBuf,
+    /// Modification time seen by the last scan.
+    seen: HashMap<PathBuf, SystemTime>,
+    /// Modification time last reported as changed.
+    reported: HashMap<PathBuf, SystemTime>,
+}
+
+impl FileWatcher {
+    pub fn new(root: &Path) -> Self {
+        Self {
+            root: root.to_path_buf(),
+            seen: HashMap::new(),
+            reported: HashMap::new(),
+        }
+    }
+
+    /// Path of `file` relative to the watched directory, or its file name
+    /// when a single file is watched.
+    pub fn relative_path<'a>(&self, file: &'a Path) -> &'a Path {
+        file.strip_prefix(&self.root)
+            .ok()
+            .filter(|p| !p.as_os_str().is_empty())
+            .or_else(|| file.file_name().map(Path::new))
+            .unwrap_or(file)
+    }
+
+    fn files(&self) -> Result<Vec<PathBuf>, Box<dyn std::error::Error>> {
+        if self.root.is_file() {
+            return Ok(vec![self.root.clone()]);
+        }
+
+        let mut files = Vec::new();
+        let mut dirs = vec![self.root.clone()];
+        while let Some(dir) = dirs.pop() {
+            for entry in fs::read_dir(&dir)? {
+                let path = entry?.path();
+                if path.is_dir() {
+                    dirs.push(path);
+                } else if is_watched_text_path(&path) {
+                    files.push(path);
+                }
+            }
+        }
+        files.sort();
+        Ok(files)
+    }
+
+    /// Returns files modified since they were last reported. A file is only
+    /// reported once its modification time has not changed between two
+    /// scans, so a save in progress is not picked up half-written. Every file
+    /// is reported once after the first two scans.
+    pub fn scan(&mut self) -> Result<Vec<PathBuf>, Box<dyn std::error::Error>> {
+        let mut changed = Vec::new();
+        let mut seen = HashMap::new();
+
+        for file in self.files()? {
+            // Files removed between listing and reading are skipped
+            let Ok(modified) = fs::metadata(&file).and_then(|m| m.modified()) else {
+                continue;
+            };
+            let stable = self.seen.get(&file) == Some(&modified);
+            if stable && self.reported.get(&file) != Some(&modified) {
+                self.reported.insert(file.clone(), modified);
+                changed.push(file.clone());
+            }
+            seen.insert(file, modified);
+        }
+
+        self.seen = seen;
+        Ok(changed)
+    }
+}